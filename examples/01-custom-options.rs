@@ -4,8 +4,7 @@ use std::env;
 #[tokio::main]
 pub async fn main() -> Result<()> {
     let file_path = env::args()
-        .skip(1)
-        .next()
+        .nth(1)
         .unwrap_or_else(|| "datasets/sales-10.csv".to_string());
     let options = ReadingOptions {
         schema_inference_depth: SchemaInferenceDepth::Percentage(0.5), // Will read 50% of the dataset to infer its schema
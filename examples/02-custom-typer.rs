@@ -5,11 +5,10 @@ use std::env;
 #[tokio::main]
 pub async fn main() -> Result<()> {
     let file_path = env::args()
-        .skip(1)
-        .next()
+        .nth(1)
         .unwrap_or_else(|| "datasets/sales-10.tsv".to_string());
     let options = ReadingOptions::default();
-    let typer = CustomTyper::default();
+    let typer = CustomTyper;
     let dataset = Dataset::read_file(file_path, options, &typer).await?;
 
     println!("Got dataset: {:#?}", dataset);
@@ -43,43 +42,38 @@ pub enum YayNay {
     Nah,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display, Default)]
 pub enum CustomColumnType {
     Maybe,
     Int,
     Float,
     Text,
+    #[default]
     Unknown,
 }
 
-impl Default for CustomColumnType {
-    fn default() -> Self {
-        CustomColumnType::Unknown
-    }
-}
-
 #[derive(Default, Debug, Clone)]
 pub struct CustomTyper;
 
 impl CustomTyper {
-    fn as_int(&self, value: &RawValue) -> Parsed<CustomValue> {
+    fn as_int(&self, value: &RawValue<'_>) -> Parsed<CustomValue> {
         value.parse_i64().map(CustomValue::Int)
     }
 
-    fn as_float(&self, value: &RawValue) -> Parsed<CustomValue> {
+    fn as_float(&self, value: &RawValue<'_>) -> Parsed<CustomValue> {
         value.parse_f64().map(CustomValue::Float)
     }
 
-    fn as_text(&self, value: &RawValue) -> CustomValue {
+    fn as_text(&self, value: &RawValue<'_>) -> CustomValue {
         CustomValue::Text(value.0.to_string())
     }
 
-    fn as_maybe(&self, value: &RawValue) -> Parsed<CustomValue> {
+    fn as_maybe(&self, value: &RawValue<'_>) -> Parsed<CustomValue> {
         match value.0.to_lowercase().trim() {
             "" => Parsed::Missing,
             "yay" => Parsed::Some(CustomValue::Maybe(YayNay::Yay)),
             "nay" => Parsed::Some(CustomValue::Maybe(YayNay::Nay)),
-            _ => Parsed::Invalid,
+            _ => Parsed::Invalid(None),
         }
     }
 }
@@ -95,13 +89,13 @@ impl Typer for CustomTyper {
         CustomColumnType::Text,
     ];
 
-    fn parse_as(&self, value: &RawValue, tag: Self::ColumnType) -> Parsed<Self::DatasetValue> {
+    fn parse_as(&self, value: &RawValue<'_>, tag: Self::ColumnType) -> Parsed<Self::DatasetValue> {
         match tag {
             CustomColumnType::Maybe => self.as_maybe(value),
             CustomColumnType::Int => self.as_int(value),
             CustomColumnType::Float => self.as_float(value),
             CustomColumnType::Text => Parsed::Some(self.as_text(value)),
-            CustomColumnType::Unknown => Parsed::Invalid,
+            CustomColumnType::Unknown => Parsed::Invalid(None),
         }
     }
 }
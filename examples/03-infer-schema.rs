@@ -4,14 +4,15 @@ use mule::{DefaultTyper, RecordParsingOptions, Result, Schema, SchemaInferenceDe
 
 #[tokio::main]
 pub async fn main() -> Result<()> {
-    let file_path = env::args().skip(1).next().expect("Missing argument");
+    let file_path = env::args().nth(1).expect("Missing argument");
     let skip_header = true;
     let inference_depth = SchemaInferenceDepth::Percentage(1.0);
     let parsing_options = RecordParsingOptions::default();
-    let typer = DefaultTyper;
+    let typer = DefaultTyper::default();
     let schema = Schema::infer(
         file_path,
         skip_header,
+        &[],
         &inference_depth,
         &parsing_options,
         &typer,
@@ -0,0 +1,145 @@
+use crate::dataset_file::DatasetFile;
+use crate::errors::Result;
+use crate::record_parsing::RecordParsingOptions;
+use std::ops::Range;
+use std::path::Path;
+use tokio_stream::StreamExt;
+
+/// Number of records, after the first, sampled while inferring the columns of an aligned table
+const ALIGNMENT_SAMPLE_SIZE: usize = 1000;
+
+/// A word of text, between runs of whitespace
+struct Word {
+    /// Range of characters the word spans
+    chars: Range<usize>,
+    /// Range of bytes the word spans
+    bytes: Range<usize>,
+}
+
+fn words(text: &str) -> Vec<Word> {
+    let mut words: Vec<Word> = vec![];
+    let mut in_word = false;
+    for (char_ix, (byte_ix, c)) in text.char_indices().enumerate() {
+        let byte_end = byte_ix + c.len_utf8();
+        match words.last_mut() {
+            Some(word) if in_word && !c.is_whitespace() => {
+                word.chars.end = char_ix + 1;
+                word.bytes.end = byte_end;
+            }
+            _ if !c.is_whitespace() => words.push(Word {
+                chars: char_ix..char_ix + 1,
+                bytes: byte_ix..byte_end,
+            }),
+            _ => {}
+        }
+        in_word = !c.is_whitespace();
+    }
+    words
+}
+
+/// Byte ranges of the cells of `text` under the columns whose headers span the given characters.
+///
+/// Each word goes to the column it overlaps the most or, failing that, the nearest one, so cells can
+/// contain single spaces and be aligned either way under their header.
+pub(crate) fn aligned_cells(text: &str, headers: &[Range<usize>]) -> Vec<Option<Range<usize>>> {
+    let mut cells: Vec<Option<Range<usize>>> = vec![None; headers.len()];
+    for word in words(text) {
+        let overlap = |header: &Range<usize>| {
+            word.chars.end.min(header.end) as isize - word.chars.start.max(header.start) as isize
+        };
+        // Picks the leftmost of equally good columns
+        let column = (0..headers.len())
+            .rev()
+            .max_by_key(|ix| overlap(&headers[*ix]));
+        if let Some(column) = column {
+            let cell = cells[column].get_or_insert(word.bytes.clone());
+            cell.end = word.bytes.end;
+        }
+    }
+    cells
+}
+
+/// Infer the columns of a whitespace-aligned table from the character ranges of the words of its first record.
+///
+/// Words of the first record are only separate columns when a character between them is blank in
+/// every sampled record, so headers such as `Mounted on` can contain single spaces.
+pub async fn infer_aligned_columns(
+    path: impl AsRef<Path>,
+    options: &RecordParsingOptions,
+) -> Result<Vec<Range<usize>>> {
+    let mut records = DatasetFile::new(path, options)
+        .read_records()
+        .await?
+        .take(ALIGNMENT_SAMPLE_SIZE + 1);
+    let header = match records.try_next().await? {
+        Some(header) => header,
+        None => return Ok(vec![]),
+    };
+    // Whether any sampled record has a word at each character
+    let mut filled: Vec<bool> = vec![];
+    while let Some(record) = records.try_next().await? {
+        for word in words(record.as_ref()) {
+            if filled.len() < word.chars.end {
+                filled.resize(word.chars.end, false);
+            }
+            filled[word.chars.clone()].fill(true);
+        }
+    }
+
+    let mut columns: Vec<Range<usize>> = vec![];
+    for word in words(header.as_ref()) {
+        match columns.last_mut() {
+            Some(column)
+                if (column.end..word.chars.start).all(|ix| filled.get(ix) == Some(&true)) =>
+            {
+                column.end = word.chars.end
+            }
+            _ => columns.push(word.chars),
+        }
+    }
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_aligned_cells() {
+        let header = "NAME     READY   STATUS       RESTARTS";
+        let headers: Vec<Range<usize>> = words(header).into_iter().map(|w| w.chars).collect();
+        assert_eq!(headers, vec![0..4, 9..14, 17..23, 30..38]);
+
+        let row = "db-0     1/1     Init Error         12";
+        let cells: Vec<Option<&str>> = aligned_cells(row, &headers)
+            .into_iter()
+            .map(|cell| cell.map(|range| &row[range]))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![Some("db-0"), Some("1/1"), Some("Init Error"), Some("12")]
+        );
+
+        let row = "web      1/1                   0";
+        let cells: Vec<Option<&str>> = aligned_cells(row, &headers)
+            .into_iter()
+            .map(|cell| cell.map(|range| &row[range]))
+            .collect();
+        assert_eq!(cells, vec![Some("web"), Some("1/1"), None, Some("0")]);
+    }
+
+    #[tokio::test]
+    pub async fn test_aligned_columns_inference() -> Result<()> {
+        let options = RecordParsingOptions::default();
+        assert_eq!(
+            infer_aligned_columns("datasets/kubectl-pods.txt", &options).await?,
+            vec![0..4, 25..30, 33..39, 52..60, 63..66]
+        );
+        assert_eq!(
+            infer_aligned_columns("datasets/df-h.txt", &options).await?,
+            vec![0..10, 16..20, 22..26, 27..32, 33..37, 38..48]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+/// Words that stand for booleans, compared to values without regard to case
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BooleanVocabulary {
+    pub true_words: Vec<String>,
+    pub false_words: Vec<String>,
+}
+
+impl Default for BooleanVocabulary {
+    fn default() -> Self {
+        BooleanVocabulary::from_words(&["true", "t", "1"], &["false", "f", "0"])
+    }
+}
+
+impl BooleanVocabulary {
+    pub fn from_words(true_words: &[&str], false_words: &[&str]) -> BooleanVocabulary {
+        BooleanVocabulary {
+            true_words: true_words.iter().map(|w| w.to_string()).collect(),
+            false_words: false_words.iter().map(|w| w.to_string()).collect(),
+        }
+    }
+
+    /// The default words along with `yes/no`, `y/n` and `on/off`
+    pub fn extended() -> BooleanVocabulary {
+        BooleanVocabulary::from_words(
+            &["true", "t", "1", "yes", "y", "on"],
+            &["false", "f", "0", "no", "n", "off"],
+        )
+    }
+
+    pub fn parse(&self, text: &str) -> Option<bool> {
+        let is_word = |word: &String| {
+            word.chars()
+                .flat_map(char::to_lowercase)
+                .eq(text.chars().flat_map(char::to_lowercase))
+        };
+        if self.true_words.iter().any(is_word) {
+            Some(true)
+        } else if self.false_words.iter().any(is_word) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Whether `text` is one of the words that are also numbers, such as `1` and `0`
+    pub(crate) fn is_numeric_word(&self, text: &str) -> bool {
+        self.parse(text).is_some() && text.parse::<i64>().is_ok()
+    }
+}
+
+/// Whether a column name reads as a yes-or-no question or a flag, as in `is_active`, `hasChildren` or `vip_flag`
+pub(crate) fn is_boolean_name(name: &str) -> bool {
+    let first_word: String = name
+        .chars()
+        .enumerate()
+        .take_while(|(ix, c)| c.is_alphabetic() && (*ix == 0 || !c.is_uppercase()))
+        .flat_map(|(_, c)| c.to_lowercase())
+        .collect();
+    matches!(first_word.as_str(), "is" | "has" | "can" | "should" | "was")
+        || name.to_lowercase().ends_with("flag")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_words() {
+        let vocabulary = BooleanVocabulary::extended();
+        assert_eq!(vocabulary.parse("Yes"), Some(true));
+        assert_eq!(vocabulary.parse("OFF"), Some(false));
+        assert_eq!(vocabulary.parse("maybe"), None);
+
+        let german = BooleanVocabulary::from_words(&["ja", "wahr"], &["nein", "falsch"]);
+        assert_eq!(german.parse("Ja"), Some(true));
+        assert_eq!(german.parse("FALSCH"), Some(false));
+        assert_eq!(german.parse("true"), None);
+    }
+
+    #[test]
+    fn test_boolean_names() {
+        for name in ["is_active", "hasChildren", "Can Vote", "vip_flag"] {
+            assert!(is_boolean_name(name), "{} should be boolean", name);
+        }
+        for name in ["issue_count", "hash", "children", "flags_count"] {
+            assert!(!is_boolean_name(name), "{} should not be boolean", name);
+        }
+    }
+}
@@ -1,50 +1,167 @@
 use crate::dataset_file::RecordsToRead;
-use crate::errors::Result;
+use crate::errors::{MuleError, Result};
+use crate::lexer::{RecordDiagnostic, RecordIssue, RecordPosition};
 use crate::record_parsing::{RecordParser, RecordParsingOptions};
 use crate::schema::Schema;
-use crate::value_parsing::Parsed;
-use crate::Typer;
+use crate::value_parsing::{InvalidValue, ParseError, Parsed, RawValue};
 use crate::{dataset_batch::DatasetBatch, dataset_file::DatasetFile};
+use crate::{DatasetValue, Typer};
 use rayon::current_num_threads;
 use rayon::prelude::*;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::path::Path;
 use tokio::task;
 use tokio_stream::StreamExt;
 
+/// A value of a column, which when invalid can say why it could not be parsed as the column type
+pub type ColumnValue<T> = Parsed<<T as Typer>::DatasetValue, <T as Typer>::ColumnType>;
+
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct Column<T: Typer> {
-    pub values: Vec<Parsed<T::DatasetValue>>,
+    pub values: Vec<ColumnValue<T>>,
+    /// Distinct values of a categorical column, in the order they were first found, which the
+    /// codes of its values refer to
+    pub categories: Vec<String>,
 }
 
 impl<T: Typer> Column<T> {
     pub fn new(n: usize) -> Column<T> {
         Column {
             values: vec![Parsed::Missing; n],
+            categories: vec![],
         }
     }
 
-    fn extend(&mut self, rhs: Self) {
+    /// Appends the values of `rhs`, recoding those of its categories into these
+    fn extend(&mut self, mut rhs: Self) {
+        if !rhs.categories.is_empty() {
+            let mut dictionary = CategoryDictionary::new(std::mem::take(&mut self.categories));
+            let codes: Vec<u32> = rhs
+                .categories
+                .iter()
+                .map(|category| dictionary.code(category))
+                .collect();
+            self.categories = dictionary.categories;
+            for value in rhs.values.iter_mut() {
+                if let Parsed::Some(value) = value {
+                    if let Some(code) = value.category_code_mut() {
+                        *code = codes[*code as usize];
+                    }
+                }
+            }
+        }
         self.values.extend(rhs.values)
     }
 
     fn empty() -> Self {
-        Column { values: vec![] }
+        Column {
+            values: vec![],
+            categories: vec![],
+        }
+    }
+
+    /// Category of the value at `row_ix`, if it is a value of a categorical column
+    pub fn category(&self, row_ix: usize) -> Option<&str> {
+        match self.values.get(row_ix)? {
+            Parsed::Some(value) => value
+                .category_code()
+                .and_then(|code| self.categories.get(code as usize))
+                .map(String::as_str),
+            _ => None,
+        }
+    }
+
+    /// Row index of each invalid value, along with its text and why it is invalid when invalid
+    /// values were kept
+    pub fn invalid_cells(
+        &self,
+    ) -> impl Iterator<Item = (usize, Option<&InvalidValue<T::ColumnType>>)> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| value.is_invalid())
+            .map(|(row_ix, value)| (row_ix, value.invalid_value()))
+    }
+}
+
+/// Codes of the categories of a column, numbered in the order they were first found
+struct CategoryDictionary {
+    categories: Vec<String>,
+    codes: HashMap<String, u32>,
+}
+
+impl CategoryDictionary {
+    fn new(categories: Vec<String>) -> CategoryDictionary {
+        let codes = (0..)
+            .zip(categories.iter())
+            .map(|(code, category)| (category.clone(), code))
+            .collect();
+        CategoryDictionary { categories, codes }
+    }
+
+    fn code(&mut self, category: &str) -> u32 {
+        if let Some(code) = self.codes.get(category) {
+            return *code;
+        }
+        let code = u32::try_from(self.categories.len()).expect("Too many categories to code");
+        self.categories.push(category.to_string());
+        self.codes.insert(category.to_string(), code);
+        code
     }
 }
 
-#[derive(PartialEq, Debug, Clone, Default)]
+/// How to handle records with more or fewer values than the dataset has columns.
+///
+/// Every record that is let through is reported in [[Columns::diagnostics]].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RaggedRows {
+    /// Fail on the first ragged record
+    Error,
+    /// Fill in missing values for short records and fail on long ones
+    Pad,
+    /// Fill in missing values for short records and drop the extra values of long ones
+    #[default]
+    Truncate,
+    /// Fill in missing values for short records and collect the extra values of long ones into [[Columns::overflow]]
+    Overflow,
+}
+
+#[derive(PartialEq, Debug, Clone)]
 pub struct Columns<T: Typer> {
     pub columns: Vec<Column<T>>,
+    /// Location in the source file of the record each row was parsed from
+    pub positions: Vec<RecordPosition>,
+    /// Malformed records that were recovered from
+    pub diagnostics: Vec<RecordDiagnostic>,
+    /// Values past the last column of each row, when collecting them with [[RaggedRows::Overflow]]
+    pub overflow: Option<Vec<Vec<ColumnValue<T>>>>,
+}
+
+impl<T: Typer> Default for Columns<T> {
+    fn default() -> Self {
+        Columns::new(0, 0)
+    }
 }
 
 impl<T: Typer> Columns<T> {
     pub fn new(columns: usize, rows: usize) -> Columns<T> {
         Columns {
             columns: vec![Column::new(rows); columns],
+            positions: Vec::with_capacity(rows),
+            diagnostics: vec![],
+            overflow: None,
         }
     }
 
     fn extend(&mut self, rhs: Self) {
+        self.positions.extend(rhs.positions);
+        self.diagnostics.extend(rhs.diagnostics);
+        if let Some(rhs_overflow) = rhs.overflow {
+            self.overflow
+                .get_or_insert_with(Vec::new)
+                .extend(rhs_overflow);
+        }
         for (col_ix, rhs_col) in rhs.columns.into_iter().enumerate() {
             let lhs_col = match self.columns.get_mut(col_ix) {
                 Some(col) => col,
@@ -64,9 +181,11 @@ impl<T: Typer> Columns<T> {
         schema: &Schema<T>,
         parsing_options: &RecordParsingOptions,
         skip_first_record: bool,
+        ragged_rows: RaggedRows,
+        keep_invalid: bool,
         typer: &T,
     ) -> Result<Columns<T>> {
-        let dataset_file = DatasetFile::new(file_path);
+        let dataset_file = DatasetFile::new(file_path, parsing_options);
         let batch_count = current_num_threads();
         let record_batches = dataset_file
             .batches(skip_first_record, RecordsToRead::All, batch_count)
@@ -80,6 +199,8 @@ impl<T: Typer> Columns<T> {
                 record_batches,
                 owned_schema,
                 owned_parsing_options,
+                ragged_rows,
+                keep_invalid,
                 owned_typer,
             )
         })
@@ -99,6 +220,8 @@ fn parse_batches_blocking<T: Typer>(
     record_batches: Vec<DatasetBatch>,
     schema: Schema<T>,
     parsing_options: RecordParsingOptions,
+    ragged_rows: RaggedRows,
+    keep_invalid: bool,
     typer: T,
 ) -> Vec<Result<Columns<T>>> {
     record_batches
@@ -108,6 +231,8 @@ fn parse_batches_blocking<T: Typer>(
                 record_batch,
                 schema.clone(),
                 parsing_options.clone(),
+                ragged_rows,
+                keep_invalid,
                 typer.clone(),
             )
         })
@@ -118,37 +243,131 @@ async fn parse_record_batch<T: Typer>(
     record_batch: DatasetBatch,
     schema: &Schema<T>,
     parsing_options: &RecordParsingOptions,
+    ragged_rows: RaggedRows,
+    keep_invalid: bool,
     typer: &T,
 ) -> Result<Columns<T>> {
-    let mut columns: Columns<T> =
-        Columns::new(schema.column_types.len(), record_batch.get_row_count());
+    let column_count = schema.column_types.len();
+    let row_count = record_batch.get_row_count();
+    let mut columns: Columns<T> = Columns::new(column_count, row_count);
+    if ragged_rows == RaggedRows::Overflow {
+        columns.overflow = Some(vec![vec![]; row_count]);
+    }
+
+    // Columns whose values are stored as codes into their categories
+    let mut dictionaries: Vec<Option<CategoryDictionary>> = schema
+        .column_types
+        .iter()
+        .map(|column_type| typer.category_value(*column_type, 0))
+        .map(|category| category.map(|_| CategoryDictionary::new(vec![])))
+        .collect();
 
     let mut records = record_batch.read_records().await?;
     let mut row_ix = 0;
 
     while let Some(record_res) = records.next().await {
         let record = record_res?;
-        let record_values = RecordParser::new(record, parsing_options);
-        for (col_ix, (value, column_type)) in
-            record_values.zip(schema.column_types.iter()).enumerate()
-        {
-            let column_value = typer.parse_as(&value, *column_type);
-            columns.columns[col_ix].values[row_ix] = column_value;
+        columns.diagnostics.extend_from_slice(record.diagnostics());
+        let record_values = RecordParser::new(&record, parsing_options);
+        let position = record_values.position();
+        columns.positions.push(position);
+        let mut value_count = 0;
+        for (col_ix, value) in record_values.enumerate() {
+            value_count += 1;
+            // Values that are missing from the record stay missing in their column
+            let value = match value {
+                Parsed::Some(value) => value,
+                _ => continue,
+            };
+            match (schema.column_types.get(col_ix), &mut columns.overflow) {
+                (Some(column_type), _) => {
+                    if let Some(dictionary) = &mut dictionaries[col_ix] {
+                        let code = dictionary.code(&value.0);
+                        let category = typer.category_value(*column_type, code);
+                        columns.columns[col_ix].values[row_ix] =
+                            category.map_or(Parsed::Invalid(None), Parsed::Some);
+                        continue;
+                    }
+                    let column_value = typer.parse_as(&value, *column_type).map_invalid(|_| {
+                        keep_invalid.then(|| invalid_value(typer, value, *column_type))
+                    });
+                    columns.columns[col_ix].values[row_ix] = column_value;
+                }
+                (None, Some(overflow)) => {
+                    overflow[row_ix].push(typer.parse(&value).map_invalid(|_| None))
+                }
+                (None, None) => {}
+            }
+        }
+        if value_count != column_count {
+            match ragged_rows {
+                RaggedRows::Error => {
+                    return Err(ragged_record_error(position, column_count, value_count))
+                }
+                RaggedRows::Pad if value_count > column_count => {
+                    return Err(ragged_record_error(position, column_count, value_count))
+                }
+                _ => columns.diagnostics.push(RecordDiagnostic {
+                    position,
+                    issue: RecordIssue::Ragged {
+                        expected: column_count,
+                        found: value_count,
+                    },
+                }),
+            }
         }
         row_ix += 1;
     }
 
+    for (column, dictionary) in columns.columns.iter_mut().zip(dictionaries) {
+        if let Some(dictionary) = dictionary {
+            column.categories = dictionary.categories;
+        }
+    }
+
     Ok(columns)
 }
 
+fn invalid_value<T: Typer>(
+    typer: &T,
+    value: RawValue<'_>,
+    column_type: T::ColumnType,
+) -> Box<InvalidValue<T::ColumnType>> {
+    let error = ParseError {
+        expected: column_type,
+        kind: typer.parse_error_kind(&value, column_type),
+    };
+    let raw = RawValue::from(value.into_owned());
+    Box::new(InvalidValue { raw, error })
+}
+
+fn ragged_record_error(position: RecordPosition, expected: usize, found: usize) -> MuleError {
+    MuleError::RaggedRecord {
+        index: position.index,
+        line: position.line,
+        expected,
+        found,
+    }
+}
+
 #[tokio::main]
 async fn parse_record_batch_blocking<T: Typer>(
     record_batch: DatasetBatch,
     schema: Schema<T>,
     parsing_options: RecordParsingOptions,
+    ragged_rows: RaggedRows,
+    keep_invalid: bool,
     typer: T,
 ) -> Result<Columns<T>> {
-    parse_record_batch(record_batch, &schema, &parsing_options, &typer).await
+    parse_record_batch(
+        record_batch,
+        &schema,
+        &parsing_options,
+        ragged_rows,
+        keep_invalid,
+        &typer,
+    )
+    .await
 }
 
 #[cfg(test)]
@@ -186,6 +405,8 @@ mod tests {
             &schema,
             &parsing_options,
             skip_first_record,
+            RaggedRows::default(),
+            false,
             &typer,
         )
         .await?;
@@ -193,6 +414,16 @@ mod tests {
         use Parsed::*;
         use Value::*;
 
+        let expected_positions = vec![148, 278, 415, 535, 663, 796, 908, 1035, 1166]
+            .into_iter()
+            .enumerate()
+            .map(|(row_ix, byte_offset)| RecordPosition {
+                byte_offset,
+                line: row_ix + 2,
+                index: row_ix + 1,
+            })
+            .collect();
+
         let expected_columns = Columns {
             columns: vec![
                 Column {
@@ -207,6 +438,7 @@ mod tests {
                         Some(Text("Sub-Saharan Africa".to_string())),
                         Some(Text("Sub-Saharan Africa".to_string())),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -220,6 +452,7 @@ mod tests {
                         Some(Text("Burkina Faso".to_string())),
                         Some(Text("Republic of the Congo".to_string())),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -233,6 +466,7 @@ mod tests {
                         Some(Text("Vegetables".to_string())),
                         Some(Text("Personal Care".to_string())),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -246,6 +480,7 @@ mod tests {
                         Some(Text("Online".to_string())),
                         Some(Text("Offline".to_string())),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -259,6 +494,7 @@ mod tests {
                         Some(Text("H".to_string())),
                         Some(Text("M".to_string())),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -272,6 +508,7 @@ mod tests {
                         Some(Text("7/17/2012".to_string())),
                         Some(Text("7/14/2015".to_string())),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -285,6 +522,7 @@ mod tests {
                         Some(Int(871543967)),
                         Some(Int(770463311)),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -298,6 +536,7 @@ mod tests {
                         Some(Text("7/27/2012".to_string())),
                         Some(Text("8/25/2015".to_string())),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -306,11 +545,12 @@ mod tests {
                         Some(Int(1779)),
                         Some(Int(8102)),
                         Some(Int(5062)),
-                        Invalid,
+                        Invalid(None),
                         Some(Int(4187)),
                         Some(Int(8082)),
                         Some(Int(6070)),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -324,6 +564,7 @@ mod tests {
                         Some(Float(154.06)),
                         Some(Float(81.73)),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -337,6 +578,7 @@ mod tests {
                         Some(Float(90.93)),
                         Some(Float(56.67)),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -350,6 +592,7 @@ mod tests {
                         Some(Float(1245112.92)),
                         Some(Float(496101.1)),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -363,6 +606,7 @@ mod tests {
                         Some(Float(734896.26)),
                         Some(Float(343986.9)),
                     ],
+                    categories: vec![],
                 },
                 Column {
                     values: vec![
@@ -376,12 +620,46 @@ mod tests {
                         Some(Float(510216.66)),
                         Some(Float(152114.2)),
                     ],
+                    categories: vec![],
                 },
             ],
+            positions: expected_positions,
+            diagnostics: vec![],
+            overflow: None,
         };
 
         assert_eq!(columns, expected_columns);
 
         Ok(())
     }
+
+    #[test]
+    fn test_extend_recodes_categories() {
+        let column = |codes: &[u32], categories: &[&str]| Column::<DefaultTyper> {
+            values: codes
+                .iter()
+                .map(|code| Parsed::Some(Value::Category(*code)))
+                .chain(Some(Parsed::Missing))
+                .collect(),
+            categories: categories.iter().map(|c| c.to_string()).collect(),
+        };
+        let mut lhs = column(&[0, 1, 0], &["Online", "Offline"]);
+        lhs.extend(column(&[0, 1, 2], &["Offline", "Direct", "Online"]));
+
+        assert_eq!(lhs.categories, vec!["Online", "Offline", "Direct"]);
+        let categories: Vec<_> = (0..lhs.values.len()).map(|ix| lhs.category(ix)).collect();
+        assert_eq!(
+            categories,
+            vec![
+                Some("Online"),
+                Some("Offline"),
+                Some("Online"),
+                None,
+                Some("Offline"),
+                Some("Direct"),
+                Some("Online"),
+                None
+            ]
+        );
+    }
 }
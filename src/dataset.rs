@@ -1,11 +1,19 @@
-use crate::column_parsing::Columns;
+use crate::aligned_table::infer_aligned_columns;
+use crate::column_parsing::{Columns, RaggedRows};
+use crate::dataset_file::DatasetFile;
 use crate::default_typer::DefaultTyper;
 use crate::errors::Result;
+use crate::fixed_width::{infer_fixed_widths, FixedWidths};
 use crate::header_parsing::Header;
-use crate::record_parsing::RecordParsingOptions;
+use crate::lexer::{ByteOrderMark, RecordTerminator, TextEncoding};
+use crate::numeric_locale::NumericLocale;
+use crate::record_parsing::{
+    MissingValues, QuoteEscape, RecordParsingOptions, Trimming, ValueSeparator,
+};
 use crate::schema::{Schema, SchemaInferenceDepth};
 use crate::separator_inference::infer_separator;
 use crate::typer::Typer;
+use regex::Regex;
 use std::path::Path;
 
 /// Strongly-typed columnar dataset
@@ -14,6 +22,10 @@ pub struct Dataset<T: Typer> {
     pub header: Option<Header>,
     pub schema: Schema<T>,
     pub columns: Columns<T>,
+    /// Encoding the dataset file was decoded with
+    pub text_encoding: TextEncoding,
+    /// Byte-order mark found at the start of the dataset file
+    pub byte_order_mark: Option<ByteOrderMark>,
 }
 
 impl<T: Typer + Send + Sync> Dataset<T> {
@@ -22,39 +34,103 @@ impl<T: Typer + Send + Sync> Dataset<T> {
         options: ReadingOptions,
         typer: &T,
     ) -> Result<Dataset<T>> {
-        let separator = match options.separator {
-            Separator::Value(value) => value,
-            Separator::Infer => infer_separator(&file_path).await?,
+        let mut parsing_options = RecordParsingOptions {
+            text_quote: options.text_quote,
+            quote_escape: options.quote_escape,
+            text_encoding: options.text_encoding,
+            record_terminator: options.record_terminator,
+            comment_prefix: options.comment_prefix,
+            skip_blank_lines: options.skip_blank_lines,
+            skip_lines: options.skip_lines,
+            lenient: options.lenient,
+            max_record_len: options.max_record_len,
+            backslash_escapes: options.backslash_escapes,
+            trimming: options.trimming,
+            missing_values: options.missing_values,
+            ..RecordParsingOptions::default()
         };
 
-        let parsing_options = RecordParsingOptions {
-            text_quote: options.text_quote,
-            text_quote_escape: options.text_quote_escape,
-            separator,
+        let (text_encoding, byte_order_mark) = DatasetFile::new(&file_path, &parsing_options)
+            .detect_encoding()
+            .await?;
+
+        let laid_out_in_columns = matches!(
+            options.separator,
+            Separator::FixedWidth(_) | Separator::InferFixedWidth | Separator::AlignedTable
+        );
+        if laid_out_in_columns || options.backslash_escapes {
+            // Values laid out in columns, and values of the PostgreSQL text format, are never quoted
+            parsing_options.text_quote = String::new();
+        }
+        match options.separator {
+            Separator::Value(value) => parsing_options.separator = value.into(),
+            Separator::AnyOf(values) => parsing_options.separator = ValueSeparator::AnyOf(values),
+            Separator::Regex(regex) => parsing_options.separator = ValueSeparator::Regex(regex),
+            Separator::Infer => {
+                parsing_options.separator = infer_separator(&file_path, &parsing_options).await?
+            }
+            Separator::Whitespace => parsing_options.separator = ValueSeparator::Whitespace,
+            Separator::AlignedTable => {
+                let headers = infer_aligned_columns(&file_path, &parsing_options).await?;
+                parsing_options.separator = ValueSeparator::Aligned(headers);
+            }
+            Separator::FixedWidth(fixed_widths) => {
+                parsing_options.fixed_widths = Some(fixed_widths)
+            }
+            Separator::InferFixedWidth => {
+                let fixed_widths = infer_fixed_widths(&file_path, &parsing_options).await?;
+                parsing_options.fixed_widths = Some(fixed_widths);
+            }
         };
+        // Fields can only be counted once the separator is known
+        parsing_options.max_fields = options.max_fields;
 
-        let header = if options.read_header {
-            Header::parse(&file_path, &parsing_options).await?
-        } else {
-            None
+        // Numbers can only be told apart from separators once the separator is known
+        let localized_typer;
+        let typer = match options.infer_numeric_locale {
+            true => {
+                let numeric_locale = NumericLocale::infer(&file_path, &parsing_options).await?;
+                localized_typer = typer.localize(&numeric_locale);
+                &localized_typer
+            }
+            false => typer,
         };
 
+        // The first record sets the number of columns, whether or not it is a header
+        let first_record = Header::parse(&file_path, &parsing_options).await?;
+        let column_count = first_record
+            .as_ref()
+            .map(|record| record.column_names.len());
         let skip_first_record = options.read_header;
-        let schema = Schema::infer(
+        let header = first_record.filter(|_| skip_first_record);
+
+        let column_names = header
+            .as_ref()
+            .map(|header| header.column_names.as_slice())
+            .unwrap_or_default();
+        let mut schema = Schema::infer(
             &file_path,
             skip_first_record,
+            column_names,
             &options.schema_inference_depth,
             &parsing_options,
             typer,
         )
         .await?;
+        if let Some(column_count) = column_count {
+            schema
+                .column_types
+                .resize(column_count, T::ColumnType::default());
+        }
 
         let columns = Columns::parse(
             &file_path,
             &schema,
             &parsing_options,
             skip_first_record,
-            &typer,
+            options.ragged_rows,
+            options.keep_invalid,
+            typer,
         )
         .await?;
 
@@ -62,6 +138,8 @@ impl<T: Typer + Send + Sync> Dataset<T> {
             header,
             schema,
             columns,
+            text_encoding,
+            byte_order_mark,
         })
     }
 }
@@ -72,7 +150,19 @@ pub type TypedDataset = Dataset<DefaultTyper>;
 #[derive(Clone, Debug)]
 pub enum Separator {
     Value(String),
+    /// Any of several values, the longest winning where more than one match
+    AnyOf(Vec<String>),
+    /// Non-empty matches of a regular expression
+    Regex(Regex),
     Infer,
+    /// Values in columns of fixed character positions
+    FixedWidth(FixedWidths),
+    /// Values in columns of fixed character positions, inferred from the whitespace between them
+    InferFixedWidth,
+    /// Values separated by one or more whitespace characters
+    Whitespace,
+    /// Values in a table aligned with whitespace, in columns positioned by the words of its first line
+    AlignedTable,
 }
 
 #[derive(Clone, Debug)]
@@ -81,7 +171,33 @@ pub struct ReadingOptions {
     pub schema_inference_depth: SchemaInferenceDepth,
     pub separator: Separator,
     pub text_quote: String,
-    pub text_quote_escape: String,
+    pub quote_escape: QuoteEscape,
+    pub text_encoding: TextEncoding,
+    pub record_terminator: RecordTerminator,
+    /// Prefix of lines that are skipped as comments
+    pub comment_prefix: Option<String>,
+    pub skip_blank_lines: bool,
+    /// Number of leading lines to skip, regardless of their content
+    pub skip_lines: usize,
+    /// Recover from malformed records instead of failing
+    pub lenient: bool,
+    /// Longest record allowed, in bytes of UTF-8 text
+    pub max_record_len: Option<usize>,
+    /// Largest number of fields allowed in a record
+    pub max_fields: Option<usize>,
+    /// Decode backslash escapes such as `\t` in values, reading `\N` as a missing value, as in the
+    /// PostgreSQL text format, which also has no quoting
+    pub backslash_escapes: bool,
+    pub ragged_rows: RaggedRows,
+    /// Read numbers in the notation most of a sample of the values are written in, see
+    /// [[NumericLocale::infer]], in place of the locale of the typer
+    pub infer_numeric_locale: bool,
+    /// Keep the text of invalid values and why they are invalid, see [[Column::invalid_cells]]
+    pub keep_invalid: bool,
+    /// Whitespace trimmed from header names and values
+    pub trimming: Trimming,
+    /// Tokens read as missing values, header names and quoted values excepted
+    pub missing_values: MissingValues,
 }
 
 impl Default for ReadingOptions {
@@ -91,7 +207,21 @@ impl Default for ReadingOptions {
             schema_inference_depth: SchemaInferenceDepth::default(),
             separator: Separator::Infer,
             text_quote: "\"".to_string(),
-            text_quote_escape: "\\".to_string(),
+            quote_escape: QuoteEscape::default(),
+            text_encoding: TextEncoding::default(),
+            record_terminator: RecordTerminator::default(),
+            comment_prefix: None,
+            skip_blank_lines: false,
+            skip_lines: 0,
+            lenient: false,
+            max_record_len: None,
+            max_fields: None,
+            backslash_escapes: false,
+            ragged_rows: RaggedRows::default(),
+            infer_numeric_locale: false,
+            keep_invalid: false,
+            trimming: Trimming::default(),
+            missing_values: MissingValues::default(),
         }
     }
 }
@@ -99,8 +229,15 @@ impl Default for ReadingOptions {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ColumnType;
+    use crate::boolean_vocabulary::BooleanVocabulary;
+    use crate::errors::MuleError;
+    use crate::lexer::{RecordDiagnostic, RecordIssue, RecordPosition};
+    use crate::record_parsing::Trim;
+    use crate::{ColumnType, InvalidValue, ParseError, ParseErrorKind, Parsed, RawValue, Value};
+    use chrono::{FixedOffset, NaiveDate, NaiveTime};
     use itertools::Itertools;
+    use rust_decimal::Decimal;
+    use std::collections::HashMap;
 
     #[tokio::test]
     pub async fn test_dataset_read_sales_10_weird() -> Result<()> {
@@ -119,9 +256,9 @@ mod tests {
                 ColumnType::Text,
                 ColumnType::Text,
                 ColumnType::Text,
-                ColumnType::Text,
+                ColumnType::Date("%m/%d/%Y"),
                 ColumnType::Int,
-                ColumnType::Text,
+                ColumnType::Date("%m/%d/%Y"),
                 ColumnType::Int,
                 ColumnType::Float,
                 ColumnType::Float,
@@ -159,11 +296,784 @@ mod tests {
         assert_eq!(header.map(|h| h.column_names.len()), Some(14));
         assert_eq!(columns.columns.len(), 14);
 
-        for column in columns.columns {
-            let all_good = column.values.iter().all(|v| v.is_some());
+        // `2-4-2015` doesn't fit the format of the other order dates
+        assert_eq!(columns.columns[5].values[5], Parsed::Invalid(None));
+        for (col_ix, column) in columns.columns.into_iter().enumerate() {
+            let all_good = (column.values.iter().enumerate())
+                .all(|(row_ix, v)| v.is_some() || (col_ix, row_ix) == (5, 5));
             assert!(all_good, "The column has invalid values! {:?}", column)
         }
 
         Ok(())
     }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_encoded_sales_10() -> Result<()> {
+        let typer = DefaultTyper::default();
+        let windows_1252 = ReadingOptions {
+            text_encoding: TextEncoding::Windows1252,
+            ..ReadingOptions::default()
+        };
+        let utf16_le = ReadingOptions {
+            text_encoding: TextEncoding::Auto,
+            ..ReadingOptions::default()
+        };
+        let cases = vec![
+            (
+                "datasets/sales-10-windows-1252.csv",
+                windows_1252,
+                TextEncoding::Windows1252,
+                None,
+            ),
+            (
+                "datasets/sales-10-utf16le.csv",
+                utf16_le,
+                TextEncoding::Utf16Le,
+                Some(ByteOrderMark::Utf16Le),
+            ),
+        ];
+
+        for (path, options, text_encoding, byte_order_mark) in cases {
+            let dataset = Dataset::read_file(path, options, &typer).await?;
+            let countries = &dataset.columns.columns[1].values;
+
+            assert_eq!(dataset.text_encoding, text_encoding);
+            assert_eq!(dataset.byte_order_mark, byte_order_mark);
+            assert_eq!(
+                dataset.header.map(|h| h.column_names[0].clone()),
+                Some("Region".to_string())
+            );
+
+            assert_eq!(dataset.schema.column_types.len(), 14);
+            assert_eq!(
+                countries[3],
+                Parsed::Some(Value::Text("São Tomé and Príncipe".to_string()))
+            );
+            assert_eq!(
+                countries[5],
+                Parsed::Some(Value::Text("Côte d’Ivoire".to_string()))
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_skipping_lines() -> Result<()> {
+        let options = ReadingOptions {
+            skip_lines: 2,
+            comment_prefix: Some("#".to_string()),
+            skip_blank_lines: true,
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let dataset = Dataset::read_file(
+            "datasets/instrument-export.csv",
+            options,
+            &DefaultTyper::default(),
+        )
+        .await?;
+
+        let expected_header = Header {
+            column_names: vec!["sample", "reading", "valid"]
+                .into_iter()
+                .map_into()
+                .collect_vec(),
+        };
+
+        assert_eq!(dataset.header, Some(expected_header));
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![ColumnType::Int, ColumnType::Float, ColumnType::Boolean]
+        );
+        assert_eq!(
+            dataset.columns.columns[1].values,
+            vec![
+                Parsed::Some(Value::Float(0.5)),
+                Parsed::Some(Value::Float(0.75)),
+                Parsed::Some(Value::Float(1.25)),
+                Parsed::Some(Value::Float(2.5)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_postgres_text_format() -> Result<()> {
+        let options = ReadingOptions {
+            read_header: false,
+            separator: Separator::Value("\t".to_string()),
+            backslash_escapes: true,
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let dataset =
+            Dataset::read_file("datasets/pg-copy.dat", options, &DefaultTyper::default()).await?;
+
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![
+                ColumnType::Int,
+                ColumnType::Text,
+                ColumnType::Text,
+                ColumnType::Boolean
+            ]
+        );
+        assert_eq!(
+            dataset.columns.columns[1].values,
+            vec![
+                Parsed::Some(Value::Text("Ada".to_string())),
+                Parsed::Missing,
+                Parsed::Some(Value::Text("\"Amazing\" Grace".to_string())),
+            ]
+        );
+        assert_eq!(
+            dataset.columns.columns[2].values,
+            vec![
+                Parsed::Some(Value::Text("first\tline".to_string())),
+                Parsed::Some(Value::Text("back\\slash".to_string())),
+                Parsed::Some(Value::Text("two\nlines".to_string())),
+            ]
+        );
+        assert_eq!(
+            dataset.columns.columns[3].values,
+            vec![
+                Parsed::Some(Value::Boolean(true)),
+                Parsed::Some(Value::Boolean(false)),
+                Parsed::Missing,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_trimmed() -> Result<()> {
+        let options = ReadingOptions {
+            trimming: Trimming {
+                default: Trim::Unquoted,
+                columns: HashMap::from([(0, Trim::Trailing)]),
+            },
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let dataset = Dataset::read_file(
+            "datasets/padded-names.csv",
+            options,
+            &DefaultTyper::default(),
+        )
+        .await?;
+
+        let expected_header = Header {
+            column_names: vec![" name", "city", "age"]
+                .into_iter()
+                .map_into()
+                .collect_vec(),
+        };
+
+        assert_eq!(dataset.header, Some(expected_header));
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![ColumnType::Text, ColumnType::Text, ColumnType::Int]
+        );
+        assert_eq!(
+            dataset.columns.columns[0].values,
+            vec![
+                Parsed::Some(Value::Text(" Ada".to_string())),
+                Parsed::Some(Value::Text("Grace".to_string())),
+            ]
+        );
+        assert_eq!(
+            dataset.columns.columns[1].values,
+            vec![
+                Parsed::Some(Value::Text("  London ".to_string())),
+                Parsed::Some(Value::Text(" New York".to_string())),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_missing_value_tokens() -> Result<()> {
+        let options = ReadingOptions {
+            missing_values: MissingValues {
+                columns: HashMap::from([(2, vec![])]),
+                ..MissingValues::common()
+            },
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let dataset = Dataset::read_file(
+            "datasets/missing-tokens.csv",
+            options,
+            &DefaultTyper::default(),
+        )
+        .await?;
+
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![ColumnType::Int, ColumnType::Float, ColumnType::Text]
+        );
+        assert_eq!(
+            dataset.columns.columns[1].values,
+            vec![
+                Parsed::Missing,
+                Parsed::Some(Value::Float(4.5)),
+                Parsed::Missing,
+                Parsed::Missing,
+                Parsed::Some(Value::Float(2.25)),
+            ]
+        );
+        assert_eq!(
+            dataset.columns.columns[2].values[1],
+            Parsed::Some(Value::Text("N/A".to_string()))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_strict_numeric_booleans() -> Result<()> {
+        let options = ReadingOptions {
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let typer = DefaultTyper {
+            boolean_vocabulary: BooleanVocabulary::extended(),
+            strict_numeric_booleans: true,
+            ..DefaultTyper::default()
+        };
+        let dataset = Dataset::read_file("datasets/flags.csv", options, &typer).await?;
+
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![
+                ColumnType::Int,
+                ColumnType::Int,
+                ColumnType::Boolean,
+                ColumnType::Boolean,
+                ColumnType::Int
+            ]
+        );
+        assert_eq!(
+            dataset.columns.columns[2].values,
+            vec![
+                Parsed::Some(Value::Boolean(true)),
+                Parsed::Some(Value::Boolean(false)),
+                Parsed::Some(Value::Boolean(true)),
+                Parsed::Some(Value::Boolean(false)),
+                Parsed::Missing,
+            ]
+        );
+        assert_eq!(
+            dataset.columns.columns[3].values,
+            vec![
+                Parsed::Some(Value::Boolean(true)),
+                Parsed::Some(Value::Boolean(false)),
+                Parsed::Some(Value::Boolean(true)),
+                Parsed::Some(Value::Boolean(false)),
+                Parsed::Some(Value::Boolean(true)),
+            ]
+        );
+        assert_eq!(
+            dataset.columns.columns[4].values,
+            [0, 1, 2, 3, 1]
+                .iter()
+                .map(|&n| Parsed::Some(Value::Int(n)))
+                .collect_vec()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_dates_and_times() -> Result<()> {
+        let options = ReadingOptions {
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let dataset =
+            Dataset::read_file("datasets/events.csv", options, &DefaultTyper::default()).await?;
+
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![
+                ColumnType::Int,
+                ColumnType::Date("%d/%m/%Y"),
+                ColumnType::DateTimeTz("%Y-%m-%dT%H:%M:%S%.f%:z"),
+                ColumnType::DateTime("%Y-%m-%d %H:%M:%S%.f"),
+                ColumnType::Time("%H:%M"),
+                ColumnType::DateTimeTz("%Y-%m-%dT%H:%M:%S%.fZ"),
+            ]
+        );
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(
+            dataset.columns.columns[1].values,
+            vec![
+                Parsed::Some(Value::Date(date(2010, 6, 5))),
+                Parsed::Some(Value::Date(date(2010, 6, 28))),
+                Parsed::Some(Value::Date(date(2010, 7, 1))),
+            ]
+        );
+        let at = date(2010, 7, 1).and_hms_opt(23, 59, 59).unwrap();
+        let offset = FixedOffset::west_opt(5 * 3600).unwrap();
+        assert_eq!(
+            dataset.columns.columns[2].values[2],
+            Parsed::Some(Value::DateTimeTz(
+                at.and_local_timezone(offset).single().unwrap()
+            ))
+        );
+        assert_eq!(
+            dataset.columns.columns[4].values[0],
+            Parsed::Some(Value::Time(NaiveTime::from_hms_opt(9, 15, 0).unwrap()))
+        );
+        let at = date(2010, 7, 1).and_hms_milli_opt(23, 59, 59, 123).unwrap();
+        assert_eq!(
+            dataset.columns.columns[5].values[2],
+            Parsed::Some(Value::DateTimeTz(at.and_utc().fixed_offset()))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_decimals() -> Result<()> {
+        let read = |max_decimal_scale| {
+            let options = ReadingOptions {
+                schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+                ..ReadingOptions::default()
+            };
+            let typer = DefaultTyper {
+                max_decimal_scale,
+                ..DefaultTyper::default()
+            };
+            async move { Dataset::read_file("datasets/sales-10.csv", options, &typer).await }
+        };
+
+        let dataset = read(Some(2)).await?;
+        let decimal = |precision| ColumnType::Decimal {
+            precision,
+            scale: 2,
+        };
+        assert_eq!(
+            dataset.schema.column_types[8..],
+            [
+                ColumnType::Int,
+                decimal(5),
+                decimal(5),
+                decimal(9),
+                decimal(9),
+                decimal(8)
+            ]
+        );
+        let total_revenue: Decimal = (dataset.columns.columns[11].values.iter())
+            .map(|value| match value {
+                Parsed::Some(Value::Decimal(decimal)) => *decimal,
+                _ => panic!("{:?} is not a decimal", value),
+            })
+            .sum();
+        assert_eq!(total_revenue, Decimal::new(1293941930, 2));
+
+        let dataset = read(Some(1)).await?;
+        assert_eq!(dataset.schema.column_types[9], ColumnType::Float);
+
+        // Values past the sample can have more integer digits, though not more decimal places
+        let options = ReadingOptions {
+            schema_inference_depth: SchemaInferenceDepth::Records(3),
+            ..ReadingOptions::default()
+        };
+        let typer = DefaultTyper {
+            max_decimal_scale: Some(2),
+            ..DefaultTyper::default()
+        };
+        let dataset = Dataset::read_file("datasets/amounts.csv", options, &typer).await?;
+        assert_eq!(dataset.schema.column_types, vec![decimal(4)]);
+        assert_eq!(
+            dataset.columns.columns[0].values[3],
+            Parsed::Some(Value::Decimal(Decimal::new(123456, 2)))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_wide_integers() -> Result<()> {
+        let options = ReadingOptions {
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let typer = DefaultTyper {
+            radix_literals: true,
+            ..DefaultTyper::default()
+        };
+        let dataset = Dataset::read_file("datasets/wide-integers.csv", options, &typer).await?;
+
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![
+                ColumnType::Int,
+                ColumnType::UInt,
+                ColumnType::Int128,
+                ColumnType::Int
+            ]
+        );
+        assert_eq!(
+            dataset.columns.columns[1].values[0],
+            Parsed::Some(Value::UInt(u64::MAX))
+        );
+        assert_eq!(
+            dataset.columns.columns[2].values[0],
+            Parsed::Some(Value::Int128(i128::MIN))
+        );
+        assert_eq!(
+            dataset.columns.columns[3].values,
+            vec![
+                Parsed::Some(Value::Int(31)),
+                Parsed::Some(Value::Int(15)),
+                Parsed::Some(Value::Int(-5)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_numeric_locale() -> Result<()> {
+        let options = || ReadingOptions {
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let spaced = NumericLocale {
+            decimal_mark: ',',
+            grouping_mark: Some(' '),
+            ..NumericLocale::default()
+        };
+        let typer = DefaultTyper::with_numeric_locale(spaced);
+        let dataset = Dataset::read_file("datasets/prices-spaced.csv", options(), &typer).await?;
+
+        assert_eq!(
+            dataset.columns.columns[1].values,
+            vec![
+                Parsed::Some(Value::Float(1234.5)),
+                Parsed::Some(Value::Float(3.2)),
+            ]
+        );
+
+        let options = ReadingOptions {
+            infer_numeric_locale: true,
+            ..options()
+        };
+        let dataset = Dataset::read_file(
+            "datasets/prices-european.csv",
+            options,
+            &DefaultTyper::default(),
+        )
+        .await?;
+
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![ColumnType::Text, ColumnType::Float, ColumnType::Float]
+        );
+        assert_eq!(
+            dataset.columns.columns[1].values,
+            vec![
+                Parsed::Some(Value::Float(1234.5)),
+                Parsed::Some(Value::Float(3.2)),
+                Parsed::Some(Value::Float(-2.0)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_invalid_cells() -> Result<()> {
+        let typer = DefaultTyper::default();
+        let read = |keep_invalid| {
+            let options = ReadingOptions {
+                schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+                keep_invalid,
+                ..ReadingOptions::default()
+            };
+            Dataset::read_file("datasets/invalid-counts.csv", options, &typer)
+        };
+
+        let dataset = read(true).await?;
+        assert_eq!(dataset.schema.column_types[1], ColumnType::Int);
+        let invalid_value = |raw: &str, kind| InvalidValue {
+            raw: RawValue::from(raw.to_string()),
+            error: ParseError {
+                expected: ColumnType::Int,
+                kind,
+            },
+        };
+        let invalid_cells: Vec<_> = dataset.columns.columns[1].invalid_cells().collect();
+        assert_eq!(
+            invalid_cells,
+            vec![
+                (2, Some(&invalid_value("five", ParseErrorKind::Malformed))),
+                (
+                    4,
+                    Some(&invalid_value(
+                        "99999999999999999999999999999999999999999",
+                        ParseErrorKind::OutOfRange
+                    ))
+                ),
+            ]
+        );
+        assert_eq!(dataset.columns.columns[0].invalid_cells().count(), 0);
+
+        let dataset = read(false).await?;
+        let invalid_cells: Vec<_> = dataset.columns.columns[1].invalid_cells().collect();
+        assert_eq!(invalid_cells, vec![(2, None), (4, None)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_categories() -> Result<()> {
+        let options = || ReadingOptions {
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let typer = DefaultTyper {
+            max_categories: Some(10),
+            ..DefaultTyper::default()
+        };
+        let dataset = Dataset::read_file("datasets/sales-100.csv", options(), &typer).await?;
+
+        assert_eq!(
+            dataset.schema.column_types[..5],
+            [
+                ColumnType::Categorical,
+                ColumnType::Text,
+                ColumnType::Text,
+                ColumnType::Categorical,
+                ColumnType::Categorical
+            ]
+        );
+        let channels = &dataset.columns.columns[3];
+        assert_eq!(channels.categories, vec!["Offline", "Online"]);
+        assert_eq!(channels.values[0], Parsed::Some(Value::Category(0)));
+        assert_eq!(dataset.columns.columns[4].categories.len(), 4);
+        assert!(dataset.columns.columns[1].categories.is_empty());
+
+        let text_typer = DefaultTyper::default();
+        let text_dataset =
+            Dataset::read_file("datasets/sales-100.csv", options(), &text_typer).await?;
+        for col_ix in [0, 3, 4] {
+            let column = &dataset.columns.columns[col_ix];
+            let text_column = &text_dataset.columns.columns[col_ix];
+            for (row_ix, text) in text_column.values.iter().enumerate() {
+                let category = column.category(row_ix).map(|c| Value::Text(c.to_string()));
+                assert_eq!(category.as_ref(), text.clone().get().as_ref());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_fixed_width() -> Result<()> {
+        let fixed_widths = FixedWidths::from_widths(&[10, 5, 7, 8]);
+        for separator in [
+            Separator::FixedWidth(fixed_widths),
+            Separator::InferFixedWidth,
+        ] {
+            let options = ReadingOptions {
+                separator,
+                schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+                ..ReadingOptions::default()
+            };
+            let dataset = Dataset::read_file(
+                "datasets/people-fixed.txt",
+                options,
+                &DefaultTyper::default(),
+            )
+            .await?;
+
+            let expected_header = Header {
+                column_names: vec!["name", "age", "score", "active"]
+                    .into_iter()
+                    .map_into()
+                    .collect_vec(),
+            };
+
+            assert_eq!(dataset.header, Some(expected_header));
+            assert_eq!(
+                dataset.schema.column_types,
+                vec![
+                    ColumnType::Text,
+                    ColumnType::Int,
+                    ColumnType::Float,
+                    ColumnType::Boolean
+                ]
+            );
+            assert_eq!(
+                dataset.columns.columns[2].values,
+                vec![
+                    Parsed::Some(Value::Float(91.5)),
+                    Parsed::Some(Value::Float(88.0)),
+                    Parsed::Missing,
+                ]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_aligned_table() -> Result<()> {
+        let options = ReadingOptions {
+            separator: Separator::AlignedTable,
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let dataset = Dataset::read_file(
+            "datasets/kubectl-pods.txt",
+            options,
+            &DefaultTyper::default(),
+        )
+        .await?;
+
+        let expected_header = Header {
+            column_names: vec!["NAME", "READY", "STATUS", "RESTARTS", "AGE"]
+                .into_iter()
+                .map_into()
+                .collect_vec(),
+        };
+
+        assert_eq!(dataset.header, Some(expected_header));
+        assert_eq!(dataset.schema.column_types[3], ColumnType::Int);
+        assert_eq!(
+            dataset.columns.columns[2].values,
+            vec![
+                Parsed::Some(Value::Text("Running".to_string())),
+                Parsed::Some(Value::Text("Init Error".to_string())),
+                Parsed::Some(Value::Text("Running".to_string())),
+            ]
+        );
+
+        let options = ReadingOptions {
+            separator: Separator::AlignedTable,
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let dataset =
+            Dataset::read_file("datasets/df-h.txt", options, &DefaultTyper::default()).await?;
+
+        let column_names = dataset.header.map(|header| header.column_names);
+        assert_eq!(
+            column_names.unwrap_or_default(),
+            vec!["Filesystem", "Size", "Used", "Avail", "Use%", "Mounted on"]
+        );
+        assert_eq!(
+            dataset.columns.columns[5].values[3],
+            Parsed::Some(Value::Text("/dev/shm".to_string()))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_padded_separators() -> Result<()> {
+        let options = ReadingOptions {
+            schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+            ..ReadingOptions::default()
+        };
+        let dataset = Dataset::read_file(
+            "datasets/padded-separators.txt",
+            options,
+            &DefaultTyper::default(),
+        )
+        .await?;
+
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![ColumnType::Int, ColumnType::Text, ColumnType::Float]
+        );
+        assert_eq!(
+            dataset.columns.columns[1].values,
+            vec![
+                Parsed::Some(Value::Text("Ada".to_string())),
+                Parsed::Some(Value::Text("Grace | Hopper".to_string())),
+                Parsed::Some(Value::Text("Linus".to_string())),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_dataset_read_ragged_rows() -> Result<()> {
+        let typer = DefaultTyper::default();
+        let read = |ragged_rows| {
+            let options = ReadingOptions {
+                ragged_rows,
+                schema_inference_depth: SchemaInferenceDepth::Percentage(1.0),
+                ..ReadingOptions::default()
+            };
+            Dataset::read_file("datasets/ragged.csv", options, &typer)
+        };
+        let ragged = |index, line, found| RecordDiagnostic {
+            position: RecordPosition {
+                byte_offset: match index {
+                    2 => 25,
+                    _ => 33,
+                },
+                line,
+                index,
+            },
+            issue: RecordIssue::Ragged { expected: 3, found },
+        };
+
+        let dataset = read(RaggedRows::Truncate).await?;
+        assert_eq!(
+            dataset.schema.column_types,
+            vec![ColumnType::Int, ColumnType::Text, ColumnType::Float]
+        );
+        assert_eq!(dataset.columns.columns[2].values[1], Parsed::Missing);
+        assert_eq!(
+            dataset.columns.diagnostics,
+            vec![ragged(2, 3, 2), ragged(3, 4, 5)]
+        );
+        assert_eq!(dataset.columns.overflow, None);
+
+        let dataset = read(RaggedRows::Overflow).await?;
+        assert_eq!(
+            dataset.columns.overflow,
+            Some(vec![
+                vec![],
+                vec![],
+                vec![
+                    Parsed::Some(Value::Text("extra".to_string())),
+                    Parsed::Some(Value::Int(7))
+                ],
+                vec![],
+            ])
+        );
+
+        assert!(matches!(
+            read(RaggedRows::Pad).await,
+            Err(MuleError::RaggedRecord {
+                index: 3,
+                line: 4,
+                expected: 3,
+                found: 5
+            })
+        ));
+        assert!(matches!(
+            read(RaggedRows::Error).await,
+            Err(MuleError::RaggedRecord {
+                index: 2,
+                line: 3,
+                expected: 3,
+                found: 2
+            })
+        ));
+
+        Ok(())
+    }
 }
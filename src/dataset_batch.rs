@@ -2,25 +2,25 @@ use crate::errors::Result;
 use crate::{dataset_file::DatasetFile, lexer::Record};
 use futures_core::stream::TryStream;
 use std::ops::RangeInclusive;
-use std::path::{Path, PathBuf};
 use tokio_stream::StreamExt;
 
 pub struct DatasetBatch {
-    file_path: PathBuf,
+    file: DatasetFile,
     records: RangeInclusive<usize>,
 }
 
 impl DatasetBatch {
-    pub fn new(file_path: impl AsRef<Path>, record_range: RangeInclusive<usize>) -> DatasetBatch {
+    pub fn new(file: DatasetFile, record_range: RangeInclusive<usize>) -> DatasetBatch {
         DatasetBatch {
-            file_path: file_path.as_ref().to_path_buf(),
+            file,
             records: record_range,
         }
     }
 
     /// Read records from this batch
     pub async fn read_records(&self) -> Result<impl TryStream<Item = Result<Record>>> {
-        let s = DatasetFile::new(&self.file_path)
+        let s = self
+            .file
             .read_records()
             .await?
             .skip(*self.records.start())
@@ -1,28 +1,45 @@
 use crate::{
     dataset_batch::DatasetBatch,
     errors::Result,
-    lexer::{Record, RecordLexer},
+    lexer::{ByteOrderMark, Record, RecordLexer, TextEncoding},
+    record_parsing::RecordParsingOptions,
 };
 use futures_core::stream::TryStream;
 use itertools::Itertools;
 use std::path::{Path, PathBuf};
 use tokio::fs::File;
-use tokio::io::BufReader;
+use tokio::io::{AsyncReadExt, BufReader};
 use tokio_stream::StreamExt;
 use tokio_util::codec::FramedRead;
 
 #[derive(Clone)]
 pub struct DatasetFile {
     path: PathBuf,
+    parsing_options: RecordParsingOptions,
 }
 
 impl DatasetFile {
-    pub fn new(path: impl AsRef<Path>) -> DatasetFile {
+    pub fn new(path: impl AsRef<Path>, parsing_options: &RecordParsingOptions) -> DatasetFile {
         DatasetFile {
             path: path.as_ref().to_owned(),
+            parsing_options: parsing_options.clone(),
         }
     }
 
+    /// Detect the byte-order mark at the start of the file and the encoding its records are decoded with
+    pub async fn detect_encoding(&self) -> Result<(TextEncoding, Option<ByteOrderMark>)> {
+        let file = File::open(&self.path).await?;
+        let mut start = Vec::with_capacity(ByteOrderMark::MAX_LEN);
+        file.take(ByteOrderMark::MAX_LEN as u64)
+            .read_to_end(&mut start)
+            .await?;
+        let configured_encoding = self.parsing_options.text_encoding;
+        let byte_order_mark = ByteOrderMark::detect(&start)
+            .filter(|bom| configured_encoding.has_byte_order_mark(*bom));
+        let text_encoding = configured_encoding.resolve(byte_order_mark);
+        Ok((text_encoding, byte_order_mark))
+    }
+
     /// Open and count the records in a file
     pub async fn count_records(&self) -> Result<usize> {
         let records = self.read_records().await?;
@@ -30,24 +47,14 @@ impl DatasetFile {
         Ok(count)
     }
 
-    #[tokio::main(flavor = "current_thread")]
-    pub async fn count_records_blocking(&self) -> Result<usize> {
-        self.count_records().await
-    }
-
     pub async fn read_records(&self) -> Result<impl TryStream<Item = Result<Record>>> {
         let reader = File::open(&self.path).await?;
         let buff = BufReader::new(reader);
-        let record_decoder = RecordLexer::new(crate::lexer::TextEncoding::Utf8);
+        let record_decoder = RecordLexer::new(&self.parsing_options);
         let stream = FramedRead::new(buff, record_decoder);
         Ok(stream.map(|res| Ok(res?)))
     }
 
-    #[tokio::main(flavor = "current_thread")]
-    pub async fn read_records_blocking(&self) -> Result<impl TryStream<Item = Result<Record>>> {
-        self.read_records().await
-    }
-
     /// Break the range of file content into batches
     pub async fn batches(
         &self,
@@ -71,7 +78,7 @@ impl DatasetFile {
         let stop = record_count;
 
         if (stop - start) < 2 {
-            return Ok(vec![DatasetBatch::new(&self.path, start..=stop)]);
+            return Ok(vec![DatasetBatch::new(self.clone(), start..=stop)]);
         }
 
         let batches = (start..stop)
@@ -80,7 +87,7 @@ impl DatasetFile {
             .map(|mut chunk| {
                 let start = chunk.next().unwrap_or(records_to_skip);
                 let stop = chunk.last().unwrap_or(start);
-                DatasetBatch::new(&self.path, start..=stop)
+                DatasetBatch::new(self.clone(), start..=stop)
             })
             .collect();
 
@@ -112,18 +119,46 @@ mod tests {
 
     #[tokio::test]
     async fn test_count_records() -> Result<()> {
+        let options = RecordParsingOptions::default();
         assert_eq!(
-            DatasetFile::new("datasets/sales-10.csv")
+            DatasetFile::new("datasets/sales-10.csv", &options)
                 .count_records()
                 .await?,
             10
         );
         assert_eq!(
-            DatasetFile::new("datasets/sales-100.tsv")
+            DatasetFile::new("datasets/sales-100.tsv", &options)
                 .count_records()
                 .await?,
             100
         );
+        assert_eq!(
+            DatasetFile::new("datasets/sales-10-cr.csv", &options)
+                .count_records()
+                .await?,
+            10
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_detect_encoding() -> Result<()> {
+        let options = RecordParsingOptions {
+            text_encoding: TextEncoding::Auto,
+            ..RecordParsingOptions::default()
+        };
+        assert_eq!(
+            DatasetFile::new("datasets/sales-10.csv", &options)
+                .detect_encoding()
+                .await?,
+            (TextEncoding::Utf8, None)
+        );
+        assert_eq!(
+            DatasetFile::new("datasets/sales-10-utf16le.csv", &options)
+                .detect_encoding()
+                .await?,
+            (TextEncoding::Utf16Le, Some(ByteOrderMark::Utf16Le))
+        );
         Ok(())
     }
 }
@@ -1,63 +1,214 @@
+use crate::boolean_vocabulary::{is_boolean_name, BooleanVocabulary};
+use crate::numeric_locale::NumericLocale;
 use crate::typer::{DatasetValue, Typer};
-use crate::value_parsing::{Parsed, RawValue};
+use crate::value_parsing::{ParseErrorKind, Parsed, RawValue};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
 use derive_more::Display;
+use rust_decimal::Decimal;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 
 /// Fully typed value
 #[derive(Clone, PartialEq, Debug)]
 pub enum Value {
     Boolean(bool),
     Int(i64),
+    UInt(u64),
+    Int128(i128),
     Float(f64),
+    Decimal(Decimal),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    DateTime(NaiveDateTime),
+    DateTimeTz(DateTime<FixedOffset>),
     Text(String),
+    /// Code of a value of a categorical column into the categories of its column
+    Category(u32),
 }
 
 impl DatasetValue<ColumnType> for Value {
+    /// Dates and times don't keep the format they were read in, so their type has the ISO 8601 one
     fn get_column_type(&self) -> ColumnType {
         match self {
             Value::Boolean(_) => ColumnType::Boolean,
             Value::Int(_) => ColumnType::Int,
+            Value::UInt(_) => ColumnType::UInt,
+            Value::Int128(_) => ColumnType::Int128,
             Value::Float(_) => ColumnType::Float,
+            Value::Decimal(decimal) => ColumnType::Decimal {
+                precision: decimal_precision(decimal),
+                scale: decimal.scale(),
+            },
+            Value::Date(_) => ColumnType::Date("%Y-%m-%d"),
+            Value::Time(_) => ColumnType::Time("%H:%M:%S%.f"),
+            Value::DateTime(_) => ColumnType::DateTime("%Y-%m-%dT%H:%M:%S%.f"),
+            Value::DateTimeTz(_) => ColumnType::DateTimeTz("%Y-%m-%dT%H:%M:%S%.f%:z"),
             Value::Text(_) => ColumnType::Text,
+            Value::Category(_) => ColumnType::Categorical,
+        }
+    }
+
+    fn category_code(&self) -> Option<u32> {
+        match self {
+            Value::Category(code) => Some(*code),
+            _ => None,
+        }
+    }
+
+    fn category_code_mut(&mut self) -> Option<&mut u32> {
+        match self {
+            Value::Category(code) => Some(code),
+            _ => None,
         }
     }
 }
 
-/// Tag of typed values
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display)]
+/// Tag of typed values. Dates and times are tagged with their `chrono` format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display, Default)]
 pub enum ColumnType {
     Boolean,
     Int,
+    UInt,
+    Int128,
     Float,
+    /// Exact numbers with `scale` decimal places, of up to `precision` digits in the sample
+    #[display(fmt = "Decimal({}, {})", precision, scale)]
+    Decimal {
+        precision: u32,
+        scale: u32,
+    },
+    #[display(fmt = "Date({})", _0)]
+    Date(&'static str),
+    #[display(fmt = "Time({})", _0)]
+    Time(&'static str),
+    #[display(fmt = "DateTime({})", _0)]
+    DateTime(&'static str),
+    #[display(fmt = "DateTimeTz({})", _0)]
+    DateTimeTz(&'static str),
     Text,
+    /// Text of a handful of distinct values, stored as codes into the categories of the column
+    Categorical,
+    #[default]
     Unknown,
 }
 
-impl Default for ColumnType {
-    fn default() -> Self {
-        ColumnType::Unknown
+impl ColumnType {
+    /// Whether both are integers of any width, or dates, times or date-times of any format
+    fn same_kind(self, other: ColumnType) -> bool {
+        let is_integer = |column_type| {
+            matches!(
+                column_type,
+                ColumnType::Int | ColumnType::UInt | ColumnType::Int128
+            )
+        };
+        (is_integer(self) && is_integer(other))
+            || matches!(
+                (self, other),
+                (ColumnType::Date(_), ColumnType::Date(_))
+                    | (ColumnType::Time(_), ColumnType::Time(_))
+                    | (ColumnType::DateTime(_), ColumnType::DateTime(_))
+                    | (ColumnType::DateTimeTz(_), ColumnType::DateTimeTz(_))
+            )
     }
 }
 
 /// Default typing scheme
-#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
-pub struct DefaultTyper;
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
+pub struct DefaultTyper {
+    /// How numbers are written
+    pub numeric_locale: NumericLocale,
+    pub boolean_vocabulary: BooleanVocabulary,
+    /// Read integers written in hexadecimal, octal or binary, as in `0x1F`, `0o17` or `0b101`
+    pub radix_literals: bool,
+    /// Infer columns of numbers with up to this many decimal places as decimals rather than floats
+    pub max_decimal_scale: Option<u32>,
+    /// Infer numbers among the boolean words, such as `1` and `0`, as booleans only when the name
+    /// of their column or its other values are booleans
+    pub strict_numeric_booleans: bool,
+    /// Infer text columns with up to this many distinct values, each found at least twice on
+    /// average, as categorical
+    pub max_categories: Option<usize>,
+}
 
 impl DefaultTyper {
-    fn as_int(&self, value: &RawValue) -> Parsed<Value> {
-        value.parse_i64().map(Value::Int)
+    pub fn with_numeric_locale(numeric_locale: NumericLocale) -> DefaultTyper {
+        DefaultTyper {
+            numeric_locale,
+            ..DefaultTyper::default()
+        }
+    }
+
+    fn as_integer<N: TryFrom<i128>>(
+        &self,
+        value: &RawValue<'_>,
+        to_value: fn(N) -> Value,
+    ) -> Parsed<Value> {
+        let integer = match value.parse_i128_with(&self.numeric_locale) {
+            Parsed::Invalid(_) if self.radix_literals => value.parse_radix_literal(),
+            integer => integer,
+        };
+        match integer.map(N::try_from) {
+            Parsed::Some(Ok(integer)) => Parsed::Some(to_value(integer)),
+            Parsed::Missing => Parsed::Missing,
+            _ => Parsed::Invalid(None),
+        }
     }
 
-    fn as_float(&self, value: &RawValue) -> Parsed<Value> {
-        value.parse_f64().map(Value::Float)
+    fn as_float(&self, value: &RawValue<'_>) -> Parsed<Value> {
+        value.parse_f64_with(&self.numeric_locale).map(Value::Float)
     }
 
-    fn as_bool(&self, value: &RawValue) -> Parsed<Value> {
-        value.parse_bool().map(Value::Boolean)
+    fn as_bool(&self, value: &RawValue<'_>) -> Parsed<Value> {
+        value
+            .parse_bool_with(&self.boolean_vocabulary)
+            .map(Value::Boolean)
     }
 
-    fn as_text(&self, value: &RawValue) -> Value {
+    fn as_text(&self, value: &RawValue<'_>) -> Value {
         Value::Text(value.0.to_string())
     }
+
+    /// Reads a decimal at the given scale, unless it has more decimal places than the scale allows.
+    /// Values outside of the sample can have more integer digits than the precision, as many as a
+    /// decimal can hold at that scale.
+    fn as_decimal(&self, value: &RawValue<'_>, scale: u32) -> Parsed<Value> {
+        match value.parse_decimal_with(&self.numeric_locale) {
+            Parsed::Some(mut decimal) => {
+                let integer_digits = decimal_precision(&decimal) - decimal.scale();
+                if decimal.scale() > scale || integer_digits + scale > MAX_DECIMAL_PRECISION {
+                    return Parsed::Invalid(None);
+                }
+                decimal.rescale(scale);
+                Parsed::Some(Value::Decimal(decimal))
+            }
+            otherwise => otherwise.map(Value::Decimal),
+        }
+    }
+
+    /// Decimal type of the value, if it is a number with few enough decimal places to infer as one
+    fn decimal_type(&self, value: &RawValue<'_>) -> Option<ColumnType> {
+        let max_scale = self.max_decimal_scale?;
+        let decimal = value.parse_decimal_with(&self.numeric_locale).get()?;
+        let decimal_type = ColumnType::Decimal {
+            precision: decimal_precision(&decimal),
+            scale: decimal.scale(),
+        };
+        Some(decimal_type).filter(|_| decimal.scale() <= max_scale)
+    }
+
+    /// Whether the value is a number among the boolean words, when those are inferred strictly
+    fn is_numeric_boolean(&self, value: &RawValue<'_>) -> bool {
+        self.strict_numeric_booleans && self.boolean_vocabulary.is_numeric_word(value.0.trim())
+    }
+
+    /// Types the value can be inferred as, in order of preference
+    fn inferable_types(&self, value: &RawValue<'_>) -> impl Iterator<Item = &'static ColumnType> {
+        let numeric_boolean = self.is_numeric_boolean(value);
+        <Self as Typer>::COLUMN_TYPES
+            .iter()
+            .filter(move |tag| !(numeric_boolean && **tag == ColumnType::Boolean))
+    }
 }
 
 impl Typer for DefaultTyper {
@@ -67,19 +218,214 @@ impl Typer for DefaultTyper {
     const COLUMN_TYPES: &'static [Self::ColumnType] = &[
         ColumnType::Boolean,
         ColumnType::Int,
+        ColumnType::UInt,
+        ColumnType::Int128,
         ColumnType::Float,
+        ColumnType::DateTimeTz("%Y-%m-%dT%H:%M:%S%.f%:z"),
+        ColumnType::DateTimeTz("%Y-%m-%d %H:%M:%S%.f%:z"),
+        ColumnType::DateTimeTz("%Y-%m-%dT%H:%M:%S%.f%z"),
+        ColumnType::DateTimeTz("%Y-%m-%d %H:%M:%S%.f%z"),
+        ColumnType::DateTimeTz("%Y-%m-%dT%H:%M:%S%.fZ"),
+        ColumnType::DateTimeTz("%Y-%m-%d %H:%M:%S%.fZ"),
+        ColumnType::DateTime("%Y-%m-%dT%H:%M:%S%.f"),
+        ColumnType::DateTime("%Y-%m-%d %H:%M:%S%.f"),
+        ColumnType::DateTime("%m/%d/%Y %H:%M:%S"),
+        ColumnType::DateTime("%d/%m/%Y %H:%M:%S"),
+        ColumnType::DateTime("%m/%d/%Y %H:%M"),
+        ColumnType::DateTime("%d/%m/%Y %H:%M"),
+        ColumnType::Date("%Y-%m-%d"),
+        ColumnType::Date("%Y/%m/%d"),
+        ColumnType::Date("%m/%d/%Y"),
+        ColumnType::Date("%d/%m/%Y"),
+        ColumnType::Date("%m-%d-%Y"),
+        ColumnType::Date("%d-%m-%Y"),
+        ColumnType::Date("%d.%m.%Y"),
+        ColumnType::Date("%d %b %Y"),
+        ColumnType::Date("%b %d, %Y"),
+        ColumnType::Time("%H:%M:%S%.f"),
+        ColumnType::Time("%H:%M"),
+        ColumnType::Time("%I:%M:%S %p"),
+        ColumnType::Time("%I:%M %p"),
         ColumnType::Text,
     ];
 
-    fn parse_as(&self, value: &RawValue, tag: Self::ColumnType) -> Parsed<Self::DatasetValue> {
+    fn parse_as(&self, value: &RawValue<'_>, tag: Self::ColumnType) -> Parsed<Self::DatasetValue> {
         match tag {
             ColumnType::Boolean => self.as_bool(value),
-            ColumnType::Int => self.as_int(value),
+            ColumnType::Int => self.as_integer(value, Value::Int),
+            ColumnType::UInt => self.as_integer(value, Value::UInt),
+            ColumnType::Int128 => self.as_integer(value, Value::Int128),
             ColumnType::Float => self.as_float(value),
-            ColumnType::Text => Parsed::Some(self.as_text(value)),
-            ColumnType::Unknown => Parsed::Invalid,
+            ColumnType::Decimal { scale, .. } => self.as_decimal(value, scale),
+            ColumnType::Date(format) => value.parse_date(format).map(Value::Date),
+            ColumnType::Time(format) => value.parse_time(format).map(Value::Time),
+            ColumnType::DateTime(format) => value.parse_datetime(format).map(Value::DateTime),
+            ColumnType::DateTimeTz(format) => {
+                value.parse_datetime_tz(format).map(Value::DateTimeTz)
+            }
+            // Only a column holds the categories its codes refer to, so a lone value stays text
+            ColumnType::Text | ColumnType::Categorical => Parsed::Some(self.as_text(value)),
+            ColumnType::Unknown => Parsed::Invalid(None),
+        }
+    }
+
+    fn localize(&self, locale: &NumericLocale) -> Self {
+        DefaultTyper {
+            numeric_locale: locale.clone(),
+            ..self.clone()
+        }
+    }
+
+    fn parse(&self, value: &RawValue<'_>) -> Parsed<Self::DatasetValue> {
+        self.inferable_types(value)
+            .map(|tag| self.parse_as(value, *tag))
+            .find(|v| v.is_some())
+            .unwrap_or(Parsed::Invalid(None))
+    }
+
+    /// An integer too large for its type, or a number with more digits than its decimal type
+    /// allows, is out of range
+    fn parse_error_kind(&self, value: &RawValue<'_>, tag: Self::ColumnType) -> ParseErrorKind {
+        let out_of_range = match tag {
+            ColumnType::Int | ColumnType::UInt | ColumnType::Int128 => {
+                let whole_number = value
+                    .parse_f64_with(&self.numeric_locale)
+                    .get()
+                    .is_some_and(|number| number.fract() == 0.0);
+                whole_number || self.radix_literals && value.parse_radix_literal().is_some()
+            }
+            ColumnType::Decimal { .. } => value.parse_decimal_with(&self.numeric_locale).is_some(),
+            _ => false,
+        };
+        if out_of_range {
+            ParseErrorKind::OutOfRange
+        } else {
+            ParseErrorKind::Malformed
+        }
+    }
+
+    /// An integer also counts toward the wider integer types, and a date or time toward the other
+    /// formats it can be read in, such as both `%m/%d/%Y` and `%d/%m/%Y` for `5/6/2010`, so the
+    /// narrowest type and the format that fit the most values win. A number among the boolean
+    /// words counts toward booleans as well when they are inferred strictly.
+    fn count_types(&self, value: &RawValue<'_>, count: &mut dyn FnMut(ColumnType)) {
+        if self.is_numeric_boolean(value) {
+            count(ColumnType::Boolean);
+        }
+        let parses = |tag: &&ColumnType| self.parse_as(value, **tag).is_some();
+        let mut tags = self.inferable_types(value);
+        if let Some(first) = tags.find(parses) {
+            let decimal_type = Some(*first)
+                .filter(|first| *first == ColumnType::Float)
+                .and_then(|_| self.decimal_type(value));
+            count(decimal_type.unwrap_or(*first));
+            tags.filter(|tag| first.same_kind(**tag))
+                .filter(parses)
+                .for_each(|tag| count(*tag));
+        }
+    }
+
+    fn column_type(
+        &self,
+        type_counts: &HashMap<ColumnType, usize>,
+        value_count: usize,
+        name: Option<&str>,
+    ) -> ColumnType {
+        let mut type_counts = merge_decimal_types(type_counts);
+        if self.strict_numeric_booleans {
+            let count = |column_type| type_counts.get(&column_type).copied().unwrap_or_default();
+            let (booleans, integers) = (count(ColumnType::Boolean), count(ColumnType::Int));
+            // Numeric boolean words count toward both booleans and integers, so the values are all
+            // boolean words when booleans are as many, and some are not numbers when booleans
+            // outnumber integers
+            let boolean_words = booleans == value_count;
+            if boolean_words && (booleans > integers || name.is_some_and(is_boolean_name)) {
+                return ColumnType::Boolean;
+            }
+            // Otherwise the booleans that are also integers are integers
+            if booleans <= integers {
+                type_counts.remove(&ColumnType::Boolean);
+            }
+        }
+        // Equally common types are settled by their order of preference
+        let preference = |column_type: &ColumnType| {
+            let column_type = match column_type {
+                ColumnType::Decimal { .. } => &ColumnType::Float,
+                column_type => column_type,
+            };
+            Self::COLUMN_TYPES
+                .iter()
+                .position(|tag| tag == column_type)
+                .unwrap_or(usize::MAX)
+        };
+        type_counts
+            .iter()
+            .max_by_key(|(column_type, count)| (**count, Reverse(preference(column_type))))
+            .map(|(column_type, _)| *column_type)
+            .unwrap_or_default()
+    }
+
+    fn max_categories(&self) -> Option<usize> {
+        self.max_categories
+    }
+
+    fn categorical_type(
+        &self,
+        column_type: ColumnType,
+        category_count: usize,
+        value_count: usize,
+    ) -> Option<ColumnType> {
+        let repeated = value_count >= 2 * category_count;
+        Some(ColumnType::Categorical).filter(|_| column_type == ColumnType::Text && repeated)
+    }
+
+    fn category_value(&self, tag: ColumnType, code: u32) -> Option<Value> {
+        Some(Value::Category(code)).filter(|_| tag == ColumnType::Categorical)
+    }
+}
+
+/// Most digits a decimal can hold
+const MAX_DECIMAL_PRECISION: u32 = 28;
+
+/// Number of digits of a decimal, those before the point included even when there are none
+fn decimal_precision(decimal: &Decimal) -> u32 {
+    let digits = decimal.mantissa().unsigned_abs().to_string().len() as u32;
+    digits.max(decimal.scale() + 1)
+}
+
+/// Counts every decimal type as one with the most integer digits and decimal places of them, or as
+/// floats if any value had too many decimal places to be a decimal
+fn merge_decimal_types(type_counts: &HashMap<ColumnType, usize>) -> HashMap<ColumnType, usize> {
+    let mut merged = HashMap::new();
+    let mut integer_digits = 0;
+    let mut scale = 0;
+    let mut decimals = 0;
+    for (column_type, count) in type_counts {
+        match column_type {
+            ColumnType::Decimal {
+                precision: p,
+                scale: s,
+            } => {
+                integer_digits = integer_digits.max(p - s);
+                scale = scale.max(*s);
+                decimals += count;
+            }
+            column_type => {
+                merged.insert(*column_type, *count);
+            }
         }
     }
+    if decimals > 0 {
+        let decimal_type = match merged.contains_key(&ColumnType::Float) {
+            true => ColumnType::Float,
+            false => ColumnType::Decimal {
+                precision: integer_digits + scale,
+                scale,
+            },
+        };
+        *merged.entry(decimal_type).or_default() += decimals;
+    }
+    merged
 }
 
 #[cfg(test)]
@@ -105,7 +451,7 @@ mod tests {
         ];
         for (raw, expected) in values {
             assert_eq!(
-                DefaultTyper.parse(&raw.into()),
+                DefaultTyper::default().parse(&raw.into()),
                 Parsed::Some(Value::Boolean(expected)),
                 "{} failed the test",
                 raw
@@ -118,7 +464,7 @@ mod tests {
         let values = vec![("4", 4), ("8", 8), ("-15", -15), ("23", 23), ("  42", 42)];
         for (raw, expected) in values {
             assert_eq!(
-                DefaultTyper.parse(&raw.into()),
+                DefaultTyper::default().parse(&raw.into()),
                 Parsed::Some(Value::Int(expected)),
                 "{} failed the test",
                 raw
@@ -142,7 +488,7 @@ mod tests {
             ("-INF", f64::NEG_INFINITY),
         ];
         for (raw, expected) in values {
-            let determined = DefaultTyper.parse(&raw.into());
+            let determined = DefaultTyper::default().parse(&raw.into());
             let is_equal = if let Parsed::Some(Value::Float(parsed)) = determined {
                 parsed.is_nan() && expected.is_nan()
                     || parsed.partial_cmp(&expected) == Some(Ordering::Equal)
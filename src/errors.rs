@@ -10,4 +10,17 @@ pub enum MuleError {
     Io(#[from] std::io::Error),
     RecordLexer(#[from] RecordLexerError),
     SchemaInference(String),
+    #[display(
+        fmt = "Record {} (line {}) has {} values where {} were expected",
+        index,
+        line,
+        found,
+        expected
+    )]
+    RaggedRecord {
+        index: usize,
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
 }
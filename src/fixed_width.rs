@@ -0,0 +1,102 @@
+use crate::dataset_file::DatasetFile;
+use crate::errors::Result;
+use crate::record_parsing::RecordParsingOptions;
+use std::ops::Range;
+use std::path::Path;
+use tokio_stream::StreamExt;
+
+/// Number of records sampled while inferring column widths
+const WIDTH_INFERENCE_SAMPLE_SIZE: usize = 1000;
+
+/// Character ranges of the columns of a fixed-width dataset
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixedWidths {
+    columns: Vec<Range<usize>>,
+}
+
+impl FixedWidths {
+    /// Columns spanning the given `start..end` character positions of each line
+    pub fn from_positions(columns: Vec<Range<usize>>) -> FixedWidths {
+        FixedWidths { columns }
+    }
+
+    /// Adjacent columns of the given widths in characters, starting at the beginning of each line
+    pub fn from_widths(widths: &[usize]) -> FixedWidths {
+        let columns = widths
+            .iter()
+            .scan(0, |start, width| {
+                let column = *start..*start + width;
+                *start = column.end;
+                Some(column)
+            })
+            .collect();
+        FixedWidths { columns }
+    }
+
+    pub fn columns(&self) -> &[Range<usize>] {
+        &self.columns
+    }
+}
+
+/// Infer column widths from the columns of whitespace that line up across a sample of the records.
+///
+/// Each column starts where a run of non-blank characters starts and the last column extends to the
+/// end of each line.
+pub async fn infer_fixed_widths(
+    path: impl AsRef<Path>,
+    options: &RecordParsingOptions,
+) -> Result<FixedWidths> {
+    // Whether every sampled record is blank at each character position
+    let mut blank: Vec<bool> = vec![];
+    let mut records = DatasetFile::new(path, options)
+        .read_records()
+        .await?
+        .take(WIDTH_INFERENCE_SAMPLE_SIZE);
+    while let Some(record_res) = records.next().await {
+        let record = record_res?;
+        for (ix, c) in record.as_ref().chars().enumerate() {
+            match blank.get_mut(ix) {
+                Some(is_blank) => *is_blank &= c == ' ',
+                None => blank.push(c == ' '),
+            }
+        }
+    }
+
+    let starts: Vec<usize> = (0..blank.len())
+        .filter(|ix| !blank[*ix] && (*ix == 0 || blank[*ix - 1]))
+        .collect();
+    let ends = starts.iter().skip(1).copied().chain(Some(usize::MAX));
+    let columns = starts
+        .iter()
+        .zip(ends)
+        .enumerate()
+        .map(|(ix, (start, end))| if ix == 0 { 0..end } else { *start..end })
+        .collect();
+    Ok(FixedWidths::from_positions(columns))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_widths_to_positions() {
+        assert_eq!(
+            FixedWidths::from_widths(&[4, 2, 6]).columns(),
+            &[0..4, 4..6, 6..12]
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_fixed_widths_inference() -> Result<()> {
+        let options = RecordParsingOptions::default();
+        assert_eq!(
+            infer_fixed_widths("datasets/people-fixed.txt", &options)
+                .await?
+                .columns(),
+            &[0..12, 12..17, 17..24, 24..usize::MAX]
+        );
+
+        Ok(())
+    }
+}
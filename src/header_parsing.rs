@@ -1,6 +1,6 @@
 use crate::dataset_file::DatasetFile;
 use crate::errors::Result;
-use crate::record_parsing::{RecordParser, RecordParsingOptions};
+use crate::record_parsing::{MissingValues, RecordParser, RecordParsingOptions};
 use itertools::Itertools;
 use std::path::Path;
 use tokio_stream::StreamExt;
@@ -15,14 +15,21 @@ impl Header {
         path: impl AsRef<Path>,
         options: &RecordParsingOptions,
     ) -> Result<Option<Header>> {
-        let header = DatasetFile::new(path)
+        // Names are never missing, whatever tokens stand for missing values
+        let names_options = RecordParsingOptions {
+            missing_values: MissingValues::default(),
+            ..options.clone()
+        };
+        let header = DatasetFile::new(path, options)
             .read_records()
             .await?
             .try_next()
             .await?
             .map(|record| {
-                let names = RecordParser::new(record, options);
-                names.map_into().collect_vec()
+                let names = RecordParser::new(&record, &names_options);
+                names
+                    .map(|name| name.map(String::from).get().unwrap_or_default())
+                    .collect_vec()
             })
             .map(|column_names| Header { column_names });
         Ok(header)
@@ -64,4 +71,17 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    pub async fn test_read_header_with_byte_order_mark() -> Result<()> {
+        let options = RecordParsingOptions::default();
+        let header = Header::parse("datasets/sales-10-bom.csv", &options).await?;
+
+        assert_eq!(
+            header.map(|h| h.column_names[0].clone()),
+            Some("Region".to_string())
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,246 @@
+use super::records::Result;
+use bytes::{BufMut, BytesMut};
+use std::char::decode_utf16;
+
+/// Text encoding of the bytes of a dataset file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1
+    Latin1,
+    Windows1252,
+    Utf16Le,
+    Utf16Be,
+    /// Detected from the byte-order mark at the start of the file, falling back to UTF-8
+    Auto,
+}
+
+/// Characters of the 0x80-0x9F range of Windows-1252. Undefined bytes map to their C1 control code points.
+static WINDOWS_1252_C1: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+/// Byte-order mark found at the start of a dataset file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ByteOrderMark {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl ByteOrderMark {
+    const ALL: [ByteOrderMark; 3] = [
+        ByteOrderMark::Utf8,
+        ByteOrderMark::Utf16Le,
+        ByteOrderMark::Utf16Be,
+    ];
+
+    /// Longest byte-order mark in bytes
+    pub(crate) const MAX_LEN: usize = 3;
+
+    pub fn bytes(self) -> &'static [u8] {
+        match self {
+            ByteOrderMark::Utf8 => &[0xEF, 0xBB, 0xBF],
+            ByteOrderMark::Utf16Le => &[0xFF, 0xFE],
+            ByteOrderMark::Utf16Be => &[0xFE, 0xFF],
+        }
+    }
+
+    /// The encoding implied by this byte-order mark
+    pub fn text_encoding(self) -> TextEncoding {
+        match self {
+            ByteOrderMark::Utf8 => TextEncoding::Utf8,
+            ByteOrderMark::Utf16Le => TextEncoding::Utf16Le,
+            ByteOrderMark::Utf16Be => TextEncoding::Utf16Be,
+        }
+    }
+
+    /// Finds the byte-order mark at the start of `bytes`
+    pub(crate) fn detect(bytes: &[u8]) -> Option<ByteOrderMark> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|bom| bytes.starts_with(bom.bytes()))
+    }
+
+    /// Whether `bytes` is too short to tell if it starts with a byte-order mark
+    pub(crate) fn is_undecided(bytes: &[u8]) -> bool {
+        Self::detect(bytes).is_none() && Self::ALL.iter().any(|bom| bom.bytes().starts_with(bytes))
+    }
+}
+
+impl TextEncoding {
+    /// The encoding to decode with, given the byte-order mark found at the start of the file.
+    ///
+    /// Only an encoding to detect is decided by the byte-order mark; a configured one is kept.
+    pub(crate) fn resolve(self, byte_order_mark: Option<ByteOrderMark>) -> TextEncoding {
+        match (byte_order_mark, self) {
+            (Some(bom), TextEncoding::Auto) => bom.text_encoding(),
+            (None, TextEncoding::Auto) => TextEncoding::Utf8,
+            (_, encoding) => encoding,
+        }
+    }
+
+    /// Whether `bom` is a byte-order mark in this encoding, rather than text that looks like one
+    pub(crate) fn has_byte_order_mark(self, bom: ByteOrderMark) -> bool {
+        self == TextEncoding::Auto || self == bom.text_encoding()
+    }
+
+    /// Number of bytes the UTF-8 `text` takes up in this encoding
+    pub(crate) fn encoded_len(self, text: &[u8]) -> usize {
+        let is_char_start = |b: &&u8| (**b & 0xC0) != 0x80;
+        match self {
+            TextEncoding::Utf8 | TextEncoding::Auto => text.len(),
+            TextEncoding::Latin1 | TextEncoding::Windows1252 => {
+                text.iter().filter(is_char_start).count()
+            }
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                let chars = text.iter().filter(is_char_start).count();
+                let surrogate_pairs = text.iter().filter(|b| **b >= 0xF0).count();
+                (chars + surrogate_pairs) * 2
+            }
+        }
+    }
+
+    /// Length of the longest prefix of complete characters in the UTF-8 `bytes`
+    pub(crate) fn utf8_prefix_len(bytes: &[u8]) -> Result<usize> {
+        match std::str::from_utf8(bytes) {
+            Ok(valid) => Ok(valid.len()),
+            Err(err) if err.error_len().is_none() => Ok(err.valid_up_to()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Decodes the longest prefix of complete characters in `bytes` into the UTF-8 `text`,
+    /// returning the number of bytes consumed.
+    pub(crate) fn decode(self, bytes: &[u8], text: &mut BytesMut) -> Result<usize> {
+        match self {
+            TextEncoding::Utf8 | TextEncoding::Auto => {
+                let len = Self::utf8_prefix_len(bytes)?;
+                text.put_slice(&bytes[..len]);
+                Ok(len)
+            }
+            TextEncoding::Latin1 => {
+                bytes.iter().for_each(|b| put_char(text, *b as char));
+                Ok(bytes.len())
+            }
+            TextEncoding::Windows1252 => {
+                bytes.iter().for_each(|b| match b {
+                    0x80..=0x9F => put_char(text, WINDOWS_1252_C1[(b - 0x80) as usize]),
+                    _ => put_char(text, *b as char),
+                });
+                Ok(bytes.len())
+            }
+            TextEncoding::Utf16Le => decode_utf16_units(bytes, u16::from_le_bytes, text),
+            TextEncoding::Utf16Be => decode_utf16_units(bytes, u16::from_be_bytes, text),
+        }
+    }
+}
+
+fn put_char(text: &mut BytesMut, c: char) {
+    text.put_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+}
+
+fn decode_utf16_units(
+    bytes: &[u8],
+    from_bytes: fn([u8; 2]) -> u16,
+    text: &mut BytesMut,
+) -> Result<usize> {
+    let mut units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect();
+    // A trailing high surrogate needs the next unit to complete its character
+    if matches!(units.last(), Some(0xD800..=0xDBFF)) {
+        units.pop();
+    }
+    for c in decode_utf16(units.iter().copied()) {
+        put_char(text, c?);
+    }
+    Ok(units.len() * 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_byte_order_marks() {
+        assert_eq!(
+            ByteOrderMark::detect(b"\xEF\xBB\xBFa,b"),
+            Some(ByteOrderMark::Utf8)
+        );
+        assert_eq!(
+            ByteOrderMark::detect(b"\xFF\xFEa\0"),
+            Some(ByteOrderMark::Utf16Le)
+        );
+        assert_eq!(
+            ByteOrderMark::detect(b"\xFE\xFF\0a"),
+            Some(ByteOrderMark::Utf16Be)
+        );
+        assert_eq!(ByteOrderMark::detect(b"a,b"), None);
+        assert!(ByteOrderMark::is_undecided(b"\xEF\xBB"));
+        assert!(ByteOrderMark::is_undecided(b""));
+        assert!(!ByteOrderMark::is_undecided(b"\xEFa"));
+        assert!(!ByteOrderMark::is_undecided(b"\xFF\xFE"));
+    }
+
+    #[test]
+    fn test_resolves_encoding() {
+        let utf16_le = Some(ByteOrderMark::Utf16Le);
+        assert_eq!(TextEncoding::Auto.resolve(None), TextEncoding::Utf8);
+        assert_eq!(TextEncoding::Latin1.resolve(None), TextEncoding::Latin1);
+        assert_eq!(TextEncoding::Auto.resolve(utf16_le), TextEncoding::Utf16Le);
+        assert_eq!(TextEncoding::Latin1.resolve(utf16_le), TextEncoding::Latin1);
+        assert!(TextEncoding::Auto.has_byte_order_mark(ByteOrderMark::Utf16Le));
+        assert!(TextEncoding::Utf8.has_byte_order_mark(ByteOrderMark::Utf8));
+        assert!(!TextEncoding::Latin1.has_byte_order_mark(ByteOrderMark::Utf8));
+    }
+
+    #[test]
+    fn test_decodes_single_byte_encodings() -> Result<()> {
+        let bytes = b"S\xE3o Tom\xE9 \x80 \x92";
+
+        let mut text = BytesMut::new();
+        assert_eq!(TextEncoding::Latin1.decode(bytes, &mut text)?, bytes.len());
+        assert_eq!(&text[..], "São Tomé \u{80} \u{92}".as_bytes());
+
+        let mut text = BytesMut::new();
+        assert_eq!(
+            TextEncoding::Windows1252.decode(bytes, &mut text)?,
+            bytes.len()
+        );
+        assert_eq!(&text[..], "São Tomé € ’".as_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decodes_complete_characters_only() -> Result<()> {
+        let mut text = BytesMut::new();
+        assert_eq!(TextEncoding::Utf8.decode(b"ab\xC3", &mut text)?, 2);
+        assert_eq!(&text[..], "ab".as_bytes());
+
+        let mut text = BytesMut::new();
+        assert_eq!(
+            TextEncoding::Utf16Le.decode(b"a\0\x3D\xD8\x00\xDE", &mut text)?,
+            6
+        );
+        assert_eq!(&text[..], "a😀".as_bytes());
+
+        let mut text = BytesMut::new();
+        assert_eq!(
+            TextEncoding::Utf16Be.decode(b"\0a\xD8\x3D\xDE", &mut text)?,
+            2
+        );
+        assert_eq!(&text[..], "a".as_bytes());
+
+        assert!(TextEncoding::Utf8.decode(b"a\xFFb", &mut text).is_err());
+
+        Ok(())
+    }
+}
@@ -1,3 +1,8 @@
+mod encoding;
 mod records;
 
-pub use records::{Record, RecordLexer, RecordLexerError, TextEncoding};
+pub use encoding::{ByteOrderMark, TextEncoding};
+pub use records::{
+    Record, RecordDiagnostic, RecordIssue, RecordLexer, RecordLexerError, RecordPosition,
+    RecordTerminator,
+};
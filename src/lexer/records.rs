@@ -1,32 +1,104 @@
+use super::encoding::{ByteOrderMark, TextEncoding};
+use crate::record_parsing::{RecordParser, RecordParsingOptions};
 use bytes::buf::Buf;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use derive_more::Display;
+use std::char::DecodeUtf16Error;
 use std::str::Utf8Error;
 use thiserror::Error;
 use tokio_util::codec::Decoder;
 
+/// Location of a record in the source file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct RecordPosition {
+    /// Offset of the first byte of the record in the file, byte-order mark included
+    pub byte_offset: usize,
+    /// 1-based number of the physical line the record starts at
+    pub line: usize,
+    /// 0-based index of the record in the file, header included
+    pub index: usize,
+}
+
+/// Malformation of a record that was recovered from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecordIssue {
+    /// A quoted value was closed at the end of its line
+    UnterminatedQuote,
+    /// The record was cut down to the maximum length
+    TooLong { max_record_len: usize },
+    /// The fields past the maximum count were dropped
+    TooManyFields { max_fields: usize },
+    /// The record has a different number of values than the dataset has columns
+    Ragged { expected: usize, found: usize },
+}
+
+/// A malformed record that was recovered from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordDiagnostic {
+    pub position: RecordPosition,
+    pub issue: RecordIssue,
+}
+
+/// A record of UTF-8 text, sharing its bytes with the buffer it was framed from
 #[derive(Clone, Debug)]
-pub struct Record(String);
+pub struct Record {
+    text: Bytes,
+    position: RecordPosition,
+    diagnostics: Vec<RecordDiagnostic>,
+}
 
 impl AsRef<str> for Record {
     fn as_ref(&self) -> &str {
-        &self.0
+        // SAFETY: records are only ever built from validated UTF-8 text
+        unsafe { std::str::from_utf8_unchecked(&self.text) }
     }
 }
 
 impl From<&str> for Record {
     fn from(s: &str) -> Self {
-        Record(s.to_string())
+        Record {
+            text: Bytes::copy_from_slice(s.as_bytes()),
+            position: RecordPosition::default(),
+            diagnostics: vec![],
+        }
     }
 }
 
 impl Record {
+    pub fn position(&self) -> RecordPosition {
+        self.position
+    }
+
     pub fn len(&self) -> usize {
-        self.as_ref().len()
+        self.text.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.as_ref().is_empty()
+        self.text.is_empty()
+    }
+
+    /// Malformations that were recovered from while lexing the record
+    pub fn diagnostics(&self) -> &[RecordDiagnostic] {
+        &self.diagnostics
+    }
+
+    fn new(text: Bytes, position: RecordPosition) -> Record {
+        Record {
+            text,
+            position,
+            diagnostics: vec![],
+        }
+    }
+
+    /// Cuts the record down to at most `len` bytes, backing off to a character boundary
+    fn truncate(&mut self, len: usize, issue: RecordIssue) {
+        // The text of a record ends with a whole character, so its end is a boundary
+        let len = floor_char_boundary(&self.text, len);
+        self.text.truncate(len);
+        self.diagnostics.push(RecordDiagnostic {
+            position: self.position,
+            issue,
+        });
     }
 }
 
@@ -34,90 +106,635 @@ impl Record {
 pub enum RecordLexerError {
     Io(#[from] std::io::Error),
     Encoding(#[from] Utf8Error),
+    Utf16Encoding(#[from] DecodeUtf16Error),
+    #[display(
+        fmt = "Quoted value starting at byte {} (line {}) is never closed",
+        byte_offset,
+        line
+    )]
+    UnterminatedQuote {
+        byte_offset: usize,
+        line: usize,
+    },
+    #[display(
+        fmt = "Record starting at byte {} (line {}) is longer than {} bytes",
+        byte_offset,
+        line,
+        max_record_len
+    )]
+    RecordTooLong {
+        byte_offset: usize,
+        line: usize,
+        max_record_len: usize,
+    },
+    #[display(
+        fmt = "Record starting at byte {} (line {}) has more than {} fields",
+        byte_offset,
+        line,
+        max_fields
+    )]
+    TooManyFields {
+        byte_offset: usize,
+        line: usize,
+        max_fields: usize,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, RecordLexerError>;
 
-/// A lexer of  RFC-4180 CSV records from UTF-8 bytes
+/// A lexer of  RFC-4180 CSV records from bytes in any of the supported [[TextEncoding]]s
 pub struct RecordLexer {
     text_encoding: TextEncoding,
+    byte_order_mark: Option<ByteOrderMark>,
+    encoding_resolved: bool,
+    framer: RecordFramer,
+    /// UTF-8 text transcoded from input that is in another encoding
+    text: BytesMut,
+    /// Length of the prefix of UTF-8 input that is known to be valid
+    validated: usize,
+    parsing_options: RecordParsingOptions,
+    /// Whether the end-of-data marker of the PostgreSQL text format was reached
+    ended: bool,
 }
 
 impl RecordLexer {
-    pub fn new(text_encoding: TextEncoding) -> RecordLexer {
-        RecordLexer { text_encoding }
+    /// Creates a lexer that decodes and frames records using the given parsing options
+    pub fn new(options: &RecordParsingOptions) -> RecordLexer {
+        RecordLexer {
+            text_encoding: options.text_encoding,
+            byte_order_mark: None,
+            encoding_resolved: false,
+            framer: RecordFramer::new(options),
+            text: BytesMut::new(),
+            validated: 0,
+            parsing_options: options.clone(),
+            ended: false,
+        }
+    }
+
+    /// Strips the byte-order mark from the start of the input and settles on the encoding to use.
+    ///
+    /// Returns false when more bytes are needed to tell.
+    fn resolve_encoding(&mut self, src: &mut BytesMut, eof: bool) -> bool {
+        if !eof && ByteOrderMark::is_undecided(src.as_ref()) {
+            return false;
+        }
+        let text_encoding = self.text_encoding;
+        self.byte_order_mark = ByteOrderMark::detect(src.as_ref())
+            .filter(|bom| text_encoding.has_byte_order_mark(*bom));
+        if let Some(bom) = self.byte_order_mark {
+            src.advance(bom.bytes().len());
+            self.framer.next_position.byte_offset = bom.bytes().len();
+        }
+        self.text_encoding = self.text_encoding.resolve(self.byte_order_mark);
+        self.encoding_resolved = true;
+        true
+    }
+
+    /// Frames the next record, reading UTF-8 input in place and transcoding any other encoding
+    fn next_record(&mut self, src: &mut BytesMut, eof: bool) -> Result<Option<Record>> {
+        if !self.encoding_resolved && !self.resolve_encoding(src, eof) {
+            return Ok(None);
+        }
+        if self.text_encoding == TextEncoding::Utf8 {
+            self.validated += TextEncoding::utf8_prefix_len(&src[self.validated..])?;
+            if eof {
+                // Fails on a character cut short by the end of the input
+                std::str::from_utf8(&src[self.validated..])?;
+            }
+            let src_len = src.len();
+            let record = self
+                .framer
+                .frame(src, self.validated, eof, self.text_encoding);
+            self.validated -= src_len - src.len();
+            record
+        } else {
+            let consumed = self.text_encoding.decode(src.as_ref(), &mut self.text)?;
+            src.advance(consumed);
+            let available = self.text.len();
+            self.framer
+                .frame(&mut self.text, available, eof, self.text_encoding)
+        }
+    }
+
+    /// Frames the next record of data, discarding the input that follows an end-of-data marker
+    fn next_data_record(&mut self, src: &mut BytesMut, eof: bool) -> Result<Option<Record>> {
+        if self.ended {
+            src.clear();
+            return Ok(None);
+        }
+        match self.next_record(src, eof)? {
+            Some(record) if self.parsing_options.backslash_escapes && record.as_ref() == "\\." => {
+                self.ended = true;
+                self.text.clear();
+                src.clear();
+                Ok(None)
+            }
+            Some(record) => self.limit_fields(record).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Fails on a record with more fields than allowed or, in lenient mode, drops the extra fields
+    fn limit_fields(&self, mut record: Record) -> Result<Record> {
+        let max_fields = match self.parsing_options.max_fields {
+            Some(max_fields) => max_fields,
+            None => return Ok(record),
+        };
+        let mut fields = RecordParser::new(&record, &self.parsing_options);
+        fields.by_ref().take(max_fields).for_each(drop);
+        let kept_len = fields.parsed_len();
+        if fields.next().is_none() {
+            return Ok(record);
+        }
+        if !self.parsing_options.lenient {
+            return Err(RecordLexerError::TooManyFields {
+                byte_offset: record.position.byte_offset,
+                line: record.position.line,
+                max_fields,
+            });
+        }
+        record.truncate(kept_len, RecordIssue::TooManyFields { max_fields });
+        Ok(record)
     }
 }
 
-#[derive(Clone, Copy)]
-pub enum TextEncoding {
-    Utf8,
+/// Sequence of characters that ends a record
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub enum RecordTerminator {
+    /// Any of `\n`, `\r\n` or a bare `\r`
+    #[default]
+    Auto,
+    Lf,
+    CrLf,
+    Cr,
+    Custom(String),
 }
 
-impl TextEncoding {
-    fn decode(self, bytes: &[u8]) -> Result<&str> {
+impl RecordTerminator {
+    /// Bytes that may start the terminator
+    fn first_bytes(&self) -> Vec<u8> {
         match self {
-            TextEncoding::Utf8 => Ok(std::str::from_utf8(bytes)?),
+            RecordTerminator::Auto => vec![LF, CR],
+            RecordTerminator::Lf => vec![LF],
+            RecordTerminator::CrLf | RecordTerminator::Cr => vec![CR],
+            RecordTerminator::Custom(terminator) => terminator.bytes().take(1).collect(),
         }
     }
 
-    fn char_byte_count(self, c: char) -> usize {
+    /// Whether `text` starts with the terminator
+    fn match_at(&self, text: &[u8], eof: bool) -> Token {
+        let is_undecided =
+            |token: &[u8]| !eof && text.len() < token.len() && token.starts_with(text);
+        let terminator_of = |token: &[u8]| {
+            if text.starts_with(token) {
+                Token::Terminator(token.len())
+            } else if is_undecided(token) {
+                Token::Undecided
+            } else {
+                Token::Other
+            }
+        };
         match self {
-            TextEncoding::Utf8 => char::len_utf8(c),
+            RecordTerminator::Auto => match text {
+                [CR, LF, ..] => Token::Terminator(2),
+                [CR] if !eof => Token::Undecided,
+                [CR, ..] | [LF, ..] => Token::Terminator(1),
+                _ => Token::Other,
+            },
+            RecordTerminator::Lf => terminator_of(&[LF]),
+            RecordTerminator::CrLf => terminator_of(&[CR, LF]),
+            RecordTerminator::Cr => terminator_of(&[CR]),
+            RecordTerminator::Custom(terminator) if terminator.is_empty() => Token::Other,
+            RecordTerminator::Custom(terminator) => terminator_of(terminator.as_bytes()),
         }
     }
+}
+
+/// Splits UTF-8 text into records, resuming its scan where the previous call left off
+struct RecordFramer {
+    text_quote: Vec<u8>,
+    /// Escape sequences that are skipped over inside quoted values
+    escape_sequences: Vec<Vec<u8>>,
+    record_terminator: RecordTerminator,
+    /// Bytes that may start a token that changes the state of the scan, outside and inside quotes
+    unquoted_candidates: Vec<u8>,
+    quoted_candidates: Vec<u8>,
+    /// Length of the prefix of the text already scanned for the end of the next record
+    scanned: usize,
+    /// Whether the scanned text ends inside a quoted value
+    quoted: bool,
+    /// Index of the quote that opened the current quoted value
+    quote_start: usize,
+    lenient: bool,
+    max_record_len: Option<usize>,
+    comment_prefix: Option<String>,
+    skip_blank_lines: bool,
+    /// Number of leading lines still to be skipped
+    lines_to_skip: usize,
+    /// Whether the scanned text belongs to a line that is being skipped
+    skipping_line: bool,
+    next_position: RecordPosition,
+}
+
+/// What the text at a candidate position of the scan turns out to be
+enum Token {
+    /// Escape sequence of the given length
+    Escape(usize),
+    Quote,
+    /// Record terminator of the given length
+    Terminator(usize),
+    Other,
+    /// More text is needed to tell
+    Undecided,
+}
+
+const CR: u8 = b'\r';
+const LF: u8 = b'\n';
+
+impl RecordFramer {
+    fn new(options: &RecordParsingOptions) -> RecordFramer {
+        let text_quote = options.text_quote.as_bytes().to_vec();
+        let escape_sequences: Vec<Vec<u8>> = options
+            .quote_escape
+            .sequences(&options.text_quote)
+            .into_iter()
+            .map(|(sequence, _)| sequence.into_bytes())
+            .collect();
+        let record_terminator = options.record_terminator.clone();
+        let mut unquoted_candidates: Vec<u8> = text_quote
+            .first()
+            .copied()
+            .into_iter()
+            .chain(record_terminator.first_bytes())
+            .collect();
+        unquoted_candidates.sort_unstable();
+        unquoted_candidates.dedup();
+        let mut quoted_candidates: Vec<u8> = text_quote
+            .first()
+            .into_iter()
+            .chain(
+                escape_sequences
+                    .iter()
+                    .filter_map(|sequence| sequence.first()),
+            )
+            .copied()
+            .collect();
+        quoted_candidates.sort_unstable();
+        quoted_candidates.dedup();
+        RecordFramer {
+            record_terminator,
+            unquoted_candidates,
+            quoted_candidates,
+            text_quote,
+            escape_sequences,
+            scanned: 0,
+            quoted: false,
+            quote_start: 0,
+            lenient: options.lenient,
+            max_record_len: options.max_record_len,
+            comment_prefix: options.comment_prefix.clone().filter(|p| !p.is_empty()),
+            skip_blank_lines: options.skip_blank_lines,
+            lines_to_skip: options.skip_lines,
+            skipping_line: false,
+            next_position: RecordPosition {
+                byte_offset: 0,
+                line: 1,
+                index: 0,
+            },
+        }
+    }
+
+    fn token_at(&self, text: &[u8], eof: bool) -> Token {
+        let quote = self.text_quote.as_slice();
+        let is_undecided =
+            |token: &[u8]| !eof && text.len() < token.len() && token.starts_with(text);
+        if self.quoted {
+            for sequence in &self.escape_sequences {
+                if text.starts_with(sequence) {
+                    return Token::Escape(sequence.len());
+                } else if is_undecided(sequence) {
+                    return Token::Undecided;
+                }
+            }
+        }
+        if !quote.is_empty() && text.starts_with(quote) {
+            Token::Quote
+        } else if !quote.is_empty() && is_undecided(quote) {
+            Token::Undecided
+        } else if self.quoted {
+            Token::Other
+        } else {
+            self.record_terminator.match_at(text, eof)
+        }
+    }
+
+    /// Splits the next record off the first `available` bytes of `text`, if they contain a complete one,
+    /// dropping any skipped lines on the way.
+    ///
+    /// At the end of the input, whatever text is left makes up the final record.
+    fn frame(
+        &mut self,
+        text: &mut BytesMut,
+        available: usize,
+        eof: bool,
+        encoding: TextEncoding,
+    ) -> Result<Option<Record>> {
+        let unavailable = text.len() - available;
+        loop {
+            let available = text.len() - unavailable;
+            if !self.skipping_line && self.scanned == 0 && !self.quoted {
+                match self.should_skip_line(&text[..available], eof) {
+                    Some(skip) => self.skipping_line = skip,
+                    None => return Ok(None),
+                }
+            }
+            if self.skipping_line {
+                if !self.skip_line(text, available, eof, encoding) {
+                    return Ok(None);
+                }
+                continue;
+            }
+            match self.frame_record(text, available, eof, encoding)? {
+                Some(record) if self.skip_blank_lines && is_blank(&record) => continue,
+                Some(record) => {
+                    self.next_position.index += 1;
+                    return Ok(Some(record));
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Whether the line at the start of `text` is to be skipped, if there is enough text to tell
+    fn should_skip_line(&self, text: &[u8], eof: bool) -> Option<bool> {
+        match &self.comment_prefix {
+            _ if self.lines_to_skip > 0 => Some(true),
+            Some(prefix) if text.starts_with(prefix.as_bytes()) => Some(true),
+            Some(prefix) if !eof && prefix.as_bytes().starts_with(text) => None,
+            _ => Some(false),
+        }
+    }
+
+    /// Drops the line at the start of `text` regardless of quotes. Returns false when more text is needed.
+    fn skip_line(
+        &mut self,
+        text: &mut BytesMut,
+        available: usize,
+        eof: bool,
+        encoding: TextEncoding,
+    ) -> bool {
+        let line_len = match self.find_terminator(&text[..available], self.scanned, eof) {
+            Some((ix, Some(len))) => ix + len,
+            // Drop what is known to belong to the line as it comes, however long the line is
+            Some((ix, None)) => {
+                self.split_off(text, ix, encoding);
+                return false;
+            }
+            None if eof && available > 0 => available,
+            None => {
+                self.split_off(text, available, encoding);
+                return false;
+            }
+        };
+        self.split_off(text, line_len, encoding);
+        self.lines_to_skip = self.lines_to_skip.saturating_sub(1);
+        self.skipping_line = false;
+        true
+    }
 
-    fn str_byte_count(self, s: &str) -> usize {
-        s.chars().map(|c| self.char_byte_count(c)).sum()
+    fn frame_record(
+        &mut self,
+        text: &mut BytesMut,
+        available: usize,
+        eof: bool,
+        encoding: TextEncoding,
+    ) -> Result<Option<Record>> {
+        loop {
+            if matches!(self.max_record_len, Some(max_len) if self.scanned > max_len) {
+                return self.frame_too_long(text, encoding).map(Some);
+            }
+            let candidates = if self.quoted {
+                &self.quoted_candidates
+            } else {
+                &self.unquoted_candidates
+            };
+            let ix = match find_any(candidates, &text[self.scanned..available]) {
+                Some(ix) => self.scanned + ix,
+                None if eof => return self.frame_last(text, available, encoding),
+                None => {
+                    self.scanned = available;
+                    return Ok(None);
+                }
+            };
+            match self.token_at(&text[ix..available], eof) {
+                Token::Escape(len) => self.scanned = ix + len,
+                Token::Quote => {
+                    if !self.quoted {
+                        self.quote_start = ix;
+                    }
+                    self.quoted = !self.quoted;
+                    self.scanned = ix + self.text_quote.len();
+                }
+                Token::Terminator(len) => {
+                    return self.split_record(text, ix, len, encoding).map(Some)
+                }
+                Token::Undecided => {
+                    self.scanned = ix;
+                    return Ok(None);
+                }
+                Token::Other => self.scanned = ix + 1,
+            }
+        }
     }
+
+    /// Frames the text left at the end of the input, which has no record terminator
+    fn frame_last(
+        &mut self,
+        text: &mut BytesMut,
+        available: usize,
+        encoding: TextEncoding,
+    ) -> Result<Option<Record>> {
+        if available == 0 {
+            return Ok(None);
+        }
+        if self.quoted {
+            let before_quote = &text[..self.quote_start];
+            if !self.lenient {
+                return Err(RecordLexerError::UnterminatedQuote {
+                    byte_offset: self.next_position.byte_offset
+                        + encoding.encoded_len(before_quote),
+                    line: self.next_position.line + count_lines(before_quote),
+                });
+            }
+            // Close the quote at the end of its line and frame the rest of the text anew
+            self.quoted = false;
+            let (record_len, terminator_len) =
+                match self.find_terminator(&text[..available], self.quote_start, true) {
+                    Some((ix, Some(len))) => (ix, len),
+                    _ => (available, 0),
+                };
+            let mut record = self.split_record(text, record_len, terminator_len, encoding)?;
+            record.diagnostics.push(RecordDiagnostic {
+                position: record.position,
+                issue: RecordIssue::UnterminatedQuote,
+            });
+            return Ok(Some(record));
+        }
+        self.split_record(text, available, 0, encoding).map(Some)
+    }
+
+    /// Handles a record that has grown past the maximum length before its end was found.
+    ///
+    /// In lenient mode, the record is cut short and the rest of its line is skipped.
+    fn frame_too_long(&mut self, text: &mut BytesMut, encoding: TextEncoding) -> Result<Record> {
+        let max_record_len = self.max_record_len.unwrap_or_default();
+        if !self.lenient {
+            return Err(self.too_long_error(max_record_len));
+        }
+        let position = self.next_position;
+        // More text follows the cut, which can't split a character that continues past it
+        let len = floor_char_boundary(text, max_record_len);
+        let framed = self.split_off(text, len, encoding);
+        self.quoted = false;
+        self.skipping_line = true;
+        let mut record = Record::new(framed, position);
+        record.truncate(max_record_len, RecordIssue::TooLong { max_record_len });
+        Ok(record)
+    }
+
+    fn too_long_error(&self, max_record_len: usize) -> RecordLexerError {
+        RecordLexerError::RecordTooLong {
+            byte_offset: self.next_position.byte_offset,
+            line: self.next_position.line,
+            max_record_len,
+        }
+    }
+
+    /// Index and length of the first record terminator in `text` after `from`, regardless of quotes.
+    ///
+    /// The length is `None` when more text is needed to tell whether a terminator starts at that index.
+    fn find_terminator(
+        &self,
+        text: &[u8],
+        from: usize,
+        eof: bool,
+    ) -> Option<(usize, Option<usize>)> {
+        let first_bytes = self.record_terminator.first_bytes();
+        let mut scanned = from;
+        while let Some(ix) = find_any(&first_bytes, &text[scanned..]) {
+            let ix = scanned + ix;
+            match self.record_terminator.match_at(&text[ix..], eof) {
+                Token::Terminator(len) => return Some((ix, Some(len))),
+                Token::Undecided => return Some((ix, None)),
+                _ => scanned = ix + 1,
+            }
+        }
+        None
+    }
+
+    /// Splits off the `record_len` bytes of `text` that make up a record and the terminator that follows
+    fn split_record(
+        &mut self,
+        text: &mut BytesMut,
+        record_len: usize,
+        terminator_len: usize,
+        encoding: TextEncoding,
+    ) -> Result<Record> {
+        let too_long = self.max_record_len.filter(|max_len| record_len > *max_len);
+        match too_long {
+            Some(max_record_len) if !self.lenient => {
+                return Err(self.too_long_error(max_record_len))
+            }
+            _ => {}
+        }
+        let position = self.next_position;
+        let framed = self.split_off(text, record_len + terminator_len, encoding);
+        let mut record = Record::new(framed.slice(..record_len), position);
+        if let Some(max_record_len) = too_long {
+            record.truncate(max_record_len, RecordIssue::TooLong { max_record_len });
+        }
+        Ok(record)
+    }
+
+    /// Splits off the first `len` bytes of `text`, moving the position of the next record past them
+    fn split_off(&mut self, text: &mut BytesMut, len: usize, encoding: TextEncoding) -> Bytes {
+        let framed = text.split_to(len).freeze();
+        self.next_position.byte_offset += encoding.encoded_len(&framed);
+        self.next_position.line += count_lines(&framed);
+        self.scanned = 0;
+        framed
+    }
+}
+
+/// The largest index of `text` up to `len` that starts a character of the UTF-8 `text`, or its end
+fn floor_char_boundary(text: &[u8], len: usize) -> usize {
+    let is_char_boundary = |ix: &usize| text.get(*ix).is_none_or(|b| (b & 0xC0) != 0x80);
+    (0..=len.min(text.len()))
+        .rev()
+        .find(is_char_boundary)
+        .unwrap_or(0)
 }
 
-static DOUBLE_QUOTES: char = '"';
-static CR: char = '\r';
-static LF: char = '\n';
+fn is_blank(record: &Record) -> bool {
+    record.text.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Number of line breaks in `text`, counting each of `\n`, `\r\n` and a bare `\r` as one
+fn count_lines(text: &[u8]) -> usize {
+    let crlfs = text.windows(2).filter(|pair| pair == &[CR, LF]).count();
+    memchr::memchr2_iter(CR, LF, text).count() - crlfs
+}
+
+fn find_any(needles: &[u8], haystack: &[u8]) -> Option<usize> {
+    match *needles {
+        [a] => memchr::memchr(a, haystack),
+        [a, b] => memchr::memchr2(a, b, haystack),
+        [a, b, c] => memchr::memchr3(a, b, c, haystack),
+        _ => None,
+    }
+}
 
 impl Decoder for RecordLexer {
     type Item = Record;
     type Error = RecordLexerError;
+
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
-        let text = self.text_encoding.decode(src.as_ref())?;
-        let mut double_quote_balance = 0;
-        let mut previous_c = '\0';
-        for (char_index, c) in text.chars().enumerate() {
-            if c == DOUBLE_QUOTES {
-                double_quote_balance = (double_quote_balance + 1) % 2;
-            } else if c == LF && double_quote_balance == 0 {
-                let delimiter = if previous_c == CR { "\r\n" } else { "\n" };
-                let delimiter_char_count = delimiter.chars().count();
-                let delimiter_byte_count = self.text_encoding.str_byte_count(&delimiter);
-                let record_char_count = char_index - (delimiter_char_count - 1);
-                let record = Record(text.chars().take(record_char_count).collect());
-                let record_byte_count = TextEncoding::Utf8.str_byte_count(&record.0);
-                src.advance(record_byte_count + delimiter_byte_count);
-                return Ok(Some(record));
-            }
-            previous_c = c;
+        self.next_data_record(src, false)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        match self.next_data_record(src, true)? {
+            Some(record) => Ok(Some(record)),
+            None if src.is_empty() => Ok(None),
+            None => Err(std::io::Error::other("bytes remaining on stream").into()),
         }
-        Ok(None)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::record_parsing::QuoteEscape;
     use tokio_stream::StreamExt;
     use tokio_util::codec::FramedRead;
 
     #[tokio::test]
     async fn test_frames_simple_records() -> Result<()> {
         let source = "name,age,gender\nname_1, 3, F \r\n name_2,5,X\n".to_string();
-        let decoder = RecordLexer::new(TextEncoding::Utf8);
+        let decoder = RecordLexer::new(&RecordParsingOptions::default());
 
         let mut framed_reader = FramedRead::new(source.as_bytes(), decoder);
 
-        assert_eq!("name,age,gender", framed_reader.next().await.unwrap()?.0);
-        assert_eq!("name_1, 3, F ", framed_reader.next().await.unwrap()?.0);
-        assert_eq!(" name_2,5,X", framed_reader.next().await.unwrap()?.0);
+        assert_eq!(
+            "name,age,gender",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert_eq!(
+            "name_1, 3, F ",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert_eq!(" name_2,5,X", framed_reader.next().await.unwrap()?.as_ref());
 
         Ok(())
     }
@@ -125,13 +742,22 @@ mod tests {
     #[tokio::test]
     async fn test_frames_records_with_quotes() -> Result<()> {
         let source = "name,age,gender\n\"name_1\", 3, F \r\n \"name 2\",5,X\n".to_string();
-        let decoder = RecordLexer::new(TextEncoding::Utf8);
+        let decoder = RecordLexer::new(&RecordParsingOptions::default());
 
         let mut framed_reader = FramedRead::new(source.as_bytes(), decoder);
 
-        assert_eq!("name,age,gender", framed_reader.next().await.unwrap()?.0);
-        assert_eq!("\"name_1\", 3, F ", framed_reader.next().await.unwrap()?.0);
-        assert_eq!(" \"name 2\",5,X", framed_reader.next().await.unwrap()?.0);
+        assert_eq!(
+            "name,age,gender",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert_eq!(
+            "\"name_1\", 3, F ",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert_eq!(
+            " \"name 2\",5,X",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
 
         Ok(())
     }
@@ -139,15 +765,21 @@ mod tests {
     #[tokio::test]
     async fn test_frames_records_with_quoted_newrecords() -> Result<()> {
         let source = "name,age,gender\n\"name\n1\", 3, F \r\n \"name \r\n 2\",5,X\n".to_string();
-        let decoder = RecordLexer::new(TextEncoding::Utf8);
+        let decoder = RecordLexer::new(&RecordParsingOptions::default());
 
         let mut framed_reader = FramedRead::new(source.as_bytes(), decoder);
 
-        assert_eq!("name,age,gender", framed_reader.next().await.unwrap()?.0);
-        assert_eq!("\"name\n1\", 3, F ", framed_reader.next().await.unwrap()?.0);
+        assert_eq!(
+            "name,age,gender",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert_eq!(
+            "\"name\n1\", 3, F ",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
         assert_eq!(
             " \"name \r\n 2\",5,X",
-            framed_reader.next().await.unwrap()?.0
+            framed_reader.next().await.unwrap()?.as_ref()
         );
 
         Ok(())
@@ -158,18 +790,564 @@ mod tests {
         let source =
             "name,\"age \"\"biological\"\"\",gender\n\"name\n1\", 3, F \r\n \"name \r\n 2\",5,X\n"
                 .to_string();
-        let decoder = RecordLexer::new(TextEncoding::Utf8);
+        let decoder = RecordLexer::new(&RecordParsingOptions::default());
 
         let mut framed_reader = FramedRead::new(source.as_bytes(), decoder);
 
         assert_eq!(
             "name,\"age \"\"biological\"\"\",gender",
-            framed_reader.next().await.unwrap()?.0
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert_eq!(
+            "\"name\n1\", 3, F ",
+            framed_reader.next().await.unwrap()?.as_ref()
         );
-        assert_eq!("\"name\n1\", 3, F ", framed_reader.next().await.unwrap()?.0);
         assert_eq!(
             " \"name \r\n 2\",5,X",
-            framed_reader.next().await.unwrap()?.0
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frames_records_with_custom_quotes() -> Result<()> {
+        let source = "name,age\n'name\n1', 3\n~~name\n2~~,5\n".to_string();
+        let single_quotes = RecordParsingOptions {
+            text_quote: "'".to_string(),
+            ..RecordParsingOptions::default()
+        };
+        let decoder = RecordLexer::new(&single_quotes);
+        let mut framed_reader = FramedRead::new(source.as_bytes(), decoder);
+
+        assert_eq!("name,age", framed_reader.next().await.unwrap()?.as_ref());
+        assert_eq!(
+            "'name\n1', 3",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert_eq!("~~name", framed_reader.next().await.unwrap()?.as_ref());
+
+        let source = "name,age\n~~name\n1~~, 3\n'name\n2',5\n".to_string();
+        let multi_char_quotes = RecordParsingOptions {
+            text_quote: "~~".to_string(),
+            quote_escape: QuoteEscape::None,
+            ..RecordParsingOptions::default()
+        };
+        let decoder = RecordLexer::new(&multi_char_quotes);
+        let mut framed_reader = FramedRead::new(source.as_bytes(), decoder);
+
+        assert_eq!("name,age", framed_reader.next().await.unwrap()?.as_ref());
+        assert_eq!(
+            "~~name\n1~~, 3",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert_eq!("'name", framed_reader.next().await.unwrap()?.as_ref());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frames_records_with_escaped_quotes() -> Result<()> {
+        let source = "name,age\n\"name \\\"\n1\", 3\nname_2,5\n".to_string();
+        let decoder = RecordLexer::new(&RecordParsingOptions::default());
+
+        let mut framed_reader = FramedRead::new(source.as_bytes(), decoder);
+
+        assert_eq!("name,age", framed_reader.next().await.unwrap()?.as_ref());
+        assert_eq!(
+            "\"name \\\"\n1\", 3",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert_eq!("name_2,5", framed_reader.next().await.unwrap()?.as_ref());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frames_records_in_every_escape_mode() -> Result<()> {
+        let source = "\"C:\\dir\\\\\",1\n\"a \"\"\nb\",2\n";
+        let frame = |quote_escape| {
+            let options = RecordParsingOptions {
+                quote_escape,
+                ..RecordParsingOptions::default()
+            };
+            FramedRead::new(source.as_bytes(), RecordLexer::new(&options))
+                .map(|record| record.map(|r| r.as_ref().to_string()))
+                .collect::<Result<Vec<String>>>()
+        };
+
+        assert_eq!(
+            frame(QuoteEscape::Backslash).await?,
+            vec!["\"C:\\dir\\\\\",1", "\"a \"\"\nb\",2"]
+        );
+        assert_eq!(
+            frame(QuoteEscape::Doubled).await?,
+            vec!["\"C:\\dir\\\\\",1", "\"a \"\"\nb\",2"]
+        );
+        assert_eq!(
+            frame(QuoteEscape::None).await?,
+            vec!["\"C:\\dir\\\\\",1", "\"a \"\"\nb\",2"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frames_records_across_chunks() -> Result<()> {
+        let options = RecordParsingOptions {
+            text_encoding: TextEncoding::Auto,
+            ..RecordParsingOptions::default()
+        };
+        let mut decoder = RecordLexer::new(&options);
+        let source: Vec<u8> = "\u{feff}név,âge\n\"ñ\n1\",3\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+
+        let mut src = BytesMut::new();
+        let mut records = vec![];
+        for byte in source {
+            src.extend_from_slice(&[byte]);
+            if let Some(record) = decoder.decode(&mut src)? {
+                records.push(record.as_ref().to_string());
+            }
+        }
+
+        assert_eq!(records, vec!["név,âge", "\"ñ\n1\",3"]);
+        assert_eq!(decoder.text_encoding, TextEncoding::Utf16Le);
+        assert_eq!(decoder.byte_order_mark, Some(ByteOrderMark::Utf16Le));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_keeps_configured_encoding_over_byte_order_mark() -> Result<()> {
+        let options = RecordParsingOptions {
+            text_encoding: TextEncoding::Latin1,
+            ..RecordParsingOptions::default()
+        };
+        let mut decoder = RecordLexer::new(&options);
+        let mut src = BytesMut::from(&b"\xEF\xBB\xBFa,\xE9\n"[..]);
+
+        let record = decoder
+            .decode_eof(&mut src)?
+            .map(|r| r.as_ref().to_string());
+        assert_eq!(record, Some("ï»¿a,é".to_string()));
+        assert_eq!(decoder.text_encoding, TextEncoding::Latin1);
+        assert_eq!(decoder.byte_order_mark, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tracks_record_positions() -> Result<()> {
+        let source = "name,age\r\n\"name\n1\",3\n\nname_2,5\n";
+        let decoder = RecordLexer::new(&RecordParsingOptions::default());
+
+        let positions: Vec<RecordPosition> = FramedRead::new(source.as_bytes(), decoder)
+            .map(|record| record.map(|r| r.position()))
+            .collect::<Result<_>>()
+            .await?;
+
+        assert_eq!(
+            positions,
+            vec![
+                RecordPosition {
+                    byte_offset: 0,
+                    line: 1,
+                    index: 0
+                },
+                RecordPosition {
+                    byte_offset: 10,
+                    line: 2,
+                    index: 1
+                },
+                RecordPosition {
+                    byte_offset: 21,
+                    line: 4,
+                    index: 2
+                },
+                RecordPosition {
+                    byte_offset: 22,
+                    line: 5,
+                    index: 3
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tracks_record_byte_offsets_in_source_encoding() -> Result<()> {
+        let source: Vec<u8> = "\u{feff}név\nâge\n"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        let options = RecordParsingOptions {
+            text_encoding: TextEncoding::Auto,
+            ..RecordParsingOptions::default()
+        };
+        let decoder = RecordLexer::new(&options);
+
+        let offsets: Vec<usize> = FramedRead::new(source.as_slice(), decoder)
+            .map(|record| record.map(|r| r.position().byte_offset))
+            .collect::<Result<_>>()
+            .await?;
+
+        assert_eq!(offsets, vec![2, 10]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resumes_scan_between_calls() -> Result<()> {
+        let mut decoder = RecordLexer::new(&RecordParsingOptions::default());
+        let mut src = BytesMut::from("name,\"a long\nquoted");
+
+        assert!(decoder.decode(&mut src)?.is_none());
+        assert_eq!(decoder.framer.scanned, src.len());
+        assert!(decoder.framer.quoted);
+
+        src.extend_from_slice(b" value\",4\nnext");
+        let record = decoder.decode(&mut src)?.unwrap();
+
+        assert_eq!(record.as_ref(), "name,\"a long\nquoted value\",4");
+        assert_eq!(src.as_ref(), b"next");
+        assert_eq!(decoder.framer.scanned, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frames_doubled_quotes_split_across_chunks() -> Result<()> {
+        let options = RecordParsingOptions {
+            quote_escape: QuoteEscape::Doubled,
+            ..RecordParsingOptions::default()
+        };
+        let mut decoder = RecordLexer::new(&options);
+        let source = "\"a \"\"\nb\"\"\",1\nc,2\n";
+
+        let mut src = BytesMut::new();
+        let mut records = vec![];
+        for byte in source.bytes() {
+            src.extend_from_slice(&[byte]);
+            if let Some(record) = decoder.decode(&mut src)? {
+                records.push(record.as_ref().to_string());
+            }
+        }
+
+        assert_eq!(records, vec!["\"a \"\"\nb\"\"\",1", "c,2"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frames_last_record_without_newline() -> Result<()> {
+        let source = "name,age\r\n\"name\n1\",3\r";
+        let decoder = RecordLexer::new(&RecordParsingOptions::default());
+
+        let mut framed_reader = FramedRead::new(source.as_bytes(), decoder);
+
+        assert_eq!("name,age", framed_reader.next().await.unwrap()?.as_ref());
+        assert_eq!(
+            "\"name\n1\",3",
+            framed_reader.next().await.unwrap()?.as_ref()
+        );
+        assert!(framed_reader.next().await.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fails_on_unterminated_quote() -> Result<()> {
+        let source = "name,age\nname_1,3\nname_2,\"5\nname_3,7\n";
+        let decoder = RecordLexer::new(&RecordParsingOptions::default());
+
+        let mut framed_reader = FramedRead::new(source.as_bytes(), decoder);
+
+        assert_eq!("name,age", framed_reader.next().await.unwrap()?.as_ref());
+        assert_eq!("name_1,3", framed_reader.next().await.unwrap()?.as_ref());
+        assert!(matches!(
+            framed_reader.next().await,
+            Some(Err(RecordLexerError::UnterminatedQuote {
+                byte_offset: 25,
+                line: 3
+            }))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_closes_unterminated_quote_at_end_of_line_when_lenient() -> Result<()> {
+        let source = "name,age\nname_1,\"3\nname_2,5\nname_3,7";
+        let options = RecordParsingOptions {
+            lenient: true,
+            ..RecordParsingOptions::default()
+        };
+        let decoder = RecordLexer::new(&options);
+
+        let records: Vec<String> = FramedRead::new(source.as_bytes(), decoder)
+            .map(|record| record.map(|r| r.as_ref().to_string()))
+            .collect::<Result<_>>()
+            .await?;
+
+        assert_eq!(
+            records,
+            vec!["name,age", "name_1,\"3", "name_2,5", "name_3,7"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fails_on_records_over_the_limits() -> Result<()> {
+        let source = "name,age\nname_1,3\nname_2,\"55555\nname_3,7\n";
+        let options = RecordParsingOptions {
+            max_record_len: Some(10),
+            ..RecordParsingOptions::default()
+        };
+        let mut framed_reader = FramedRead::new(source.as_bytes(), RecordLexer::new(&options));
+
+        assert_eq!("name,age", framed_reader.next().await.unwrap()?.as_ref());
+        assert_eq!("name_1,3", framed_reader.next().await.unwrap()?.as_ref());
+        assert!(matches!(
+            framed_reader.next().await,
+            Some(Err(RecordLexerError::RecordTooLong {
+                byte_offset: 18,
+                line: 3,
+                max_record_len: 10
+            }))
+        ));
+
+        let source = "name,age\nname_1,3,x\n";
+        let options = RecordParsingOptions {
+            max_fields: Some(2),
+            ..RecordParsingOptions::default()
+        };
+        let mut framed_reader = FramedRead::new(source.as_bytes(), RecordLexer::new(&options));
+
+        assert_eq!("name,age", framed_reader.next().await.unwrap()?.as_ref());
+        assert!(matches!(
+            framed_reader.next().await,
+            Some(Err(RecordLexerError::TooManyFields {
+                byte_offset: 9,
+                line: 2,
+                max_fields: 2
+            }))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_truncates_records_over_the_limits_when_lenient() -> Result<()> {
+        let source = "name,age\nname_1,3,x,y\nname_2,\"5\n5555\nname_3,7\n";
+        let options = RecordParsingOptions {
+            max_record_len: Some(10),
+            max_fields: Some(2),
+            lenient: true,
+            ..RecordParsingOptions::default()
+        };
+        let decoder = RecordLexer::new(&options);
+
+        let records: Vec<Record> = FramedRead::new(source.as_bytes(), decoder)
+            .collect::<Result<_>>()
+            .await?;
+
+        let texts: Vec<&str> = records.iter().map(|r| r.as_ref()).collect();
+        assert_eq!(
+            texts,
+            vec!["name,age", "name_1,3", "name_2,\"5\n", "name_3,7"]
+        );
+
+        let diagnostics: Vec<RecordDiagnostic> = records
+            .iter()
+            .flat_map(|r| r.diagnostics().to_vec())
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                RecordDiagnostic {
+                    position: records[1].position(),
+                    issue: RecordIssue::TooLong { max_record_len: 10 },
+                },
+                RecordDiagnostic {
+                    position: records[1].position(),
+                    issue: RecordIssue::TooManyFields { max_fields: 2 },
+                },
+                RecordDiagnostic {
+                    position: records[2].position(),
+                    issue: RecordIssue::TooLong { max_record_len: 10 },
+                },
+            ]
+        );
+        assert_eq!(records[3].position().line, 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_truncates_records_between_characters() -> Result<()> {
+        let source = "name_2,\"5é5555\nname_3,7\n";
+        let options = RecordParsingOptions {
+            max_record_len: Some(10),
+            lenient: true,
+            ..RecordParsingOptions::default()
+        };
+        let decoder = RecordLexer::new(&options);
+
+        let records: Vec<Record> = FramedRead::new(source.as_bytes(), decoder)
+            .collect::<Result<_>>()
+            .await?;
+
+        let texts: Vec<&str> = records.iter().map(|r| r.as_ref()).collect();
+        assert_eq!(texts, vec!["name_2,\"5", "name_3,7"]);
+        assert_eq!(records[1].position().byte_offset, 16);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frames_records_with_any_newline() -> Result<()> {
+        let source = "a,b\r1,\"x\ry\"\r\n2,z\n3,w\r";
+        let decoder = RecordLexer::new(&RecordParsingOptions::default());
+
+        let records: Vec<(String, usize)> = FramedRead::new(source.as_bytes(), decoder)
+            .map(|record| record.map(|r| (r.as_ref().to_string(), r.position().line)))
+            .collect::<Result<_>>()
+            .await?;
+
+        assert_eq!(
+            records,
+            vec![
+                ("a,b".to_string(), 1),
+                ("1,\"x\ry\"".to_string(), 2),
+                ("2,z".to_string(), 4),
+                ("3,w".to_string(), 5),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_frames_records_with_configured_terminator() -> Result<()> {
+        let cases = vec![
+            (RecordTerminator::Lf, "a\r\nb\rc\n", vec!["a\r", "b\rc"]),
+            (
+                RecordTerminator::CrLf,
+                "a\r\nb\rc\nd\r\n",
+                vec!["a", "b\rc\nd"],
+            ),
+            (RecordTerminator::Cr, "a\rb\nc\r", vec!["a", "b\nc"]),
+            (
+                RecordTerminator::Custom("\u{1e}".to_string()),
+                "a\u{1f}b\u{1e}c\nd\u{1f}\"e\u{1e}\"\u{1e}",
+                vec!["a\u{1f}b", "c\nd\u{1f}\"e\u{1e}\""],
+            ),
+            (
+                RecordTerminator::Custom("<>".to_string()),
+                "a<b<>c<>",
+                vec!["a<b", "c"],
+            ),
+        ];
+
+        for (record_terminator, source, expected) in cases {
+            let options = RecordParsingOptions {
+                record_terminator,
+                ..RecordParsingOptions::default()
+            };
+            let mut decoder = RecordLexer::new(&options);
+            let mut src = BytesMut::new();
+            let mut records = vec![];
+            for byte in source.bytes() {
+                src.extend_from_slice(&[byte]);
+                if let Some(record) = decoder.decode(&mut src)? {
+                    records.push(record.as_ref().to_string());
+                }
+            }
+            while let Some(record) = decoder.decode_eof(&mut src)? {
+                records.push(record.as_ref().to_string());
+            }
+
+            assert_eq!(records, expected);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_skips_whitespace_lines() -> Result<()> {
+        let source = "a,1\n\t\t\n \t \x0c\nb,2\n\t";
+        let options = RecordParsingOptions {
+            skip_blank_lines: true,
+            ..RecordParsingOptions::default()
+        };
+        let mut decoder = RecordLexer::new(&options);
+
+        let mut src = BytesMut::from(source);
+        let mut records = vec![];
+        while let Some(record) = decoder.decode_eof(&mut src)? {
+            records.push(record.as_ref().to_string());
+        }
+
+        assert_eq!(records, vec!["a,1", "b,2"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_skips_preamble_comments_and_blank_lines() -> Result<()> {
+        let source = "Exported by \"Instrument 5\n\n# units: \"mm\nname,size\n\n# calibrated\na,\"1\n\n2\"\n  \nb,3";
+        let options = RecordParsingOptions {
+            skip_lines: 2,
+            comment_prefix: Some("#".to_string()),
+            skip_blank_lines: true,
+            ..RecordParsingOptions::default()
+        };
+        let mut decoder = RecordLexer::new(&options);
+
+        let mut src = BytesMut::new();
+        let mut records = vec![];
+        for byte in source.bytes() {
+            src.extend_from_slice(&[byte]);
+            if let Some(record) = decoder.decode(&mut src)? {
+                records.push((record.as_ref().to_string(), record.position()));
+            }
+        }
+        while let Some(record) = decoder.decode_eof(&mut src)? {
+            records.push((record.as_ref().to_string(), record.position()));
+        }
+
+        assert_eq!(
+            records,
+            vec![
+                (
+                    "name,size".to_string(),
+                    RecordPosition {
+                        byte_offset: 40,
+                        line: 4,
+                        index: 0
+                    }
+                ),
+                (
+                    "a,\"1\n\n2\"".to_string(),
+                    RecordPosition {
+                        byte_offset: 64,
+                        line: 7,
+                        index: 1
+                    }
+                ),
+                (
+                    "b,3".to_string(),
+                    RecordPosition {
+                        byte_offset: 76,
+                        line: 11,
+                        index: 2
+                    }
+                ),
+            ]
         );
 
         Ok(())
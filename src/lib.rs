@@ -1,6 +1,8 @@
 #![deny(warnings)]
 #![deny(clippy::all)]
 
+mod aligned_table;
+mod boolean_vocabulary;
 mod column_parsing;
 mod dataset;
 mod dataset_batch;
@@ -8,20 +10,30 @@ mod dataset_file;
 mod default_typer;
 mod defaults;
 mod errors;
+mod fixed_width;
 mod header_parsing;
 mod lexer;
+mod numeric_locale;
 mod record_parsing;
 mod schema;
 mod separator_inference;
 mod typer;
 mod value_parsing;
 
-pub use column_parsing::{Column, Columns};
+pub use boolean_vocabulary::BooleanVocabulary;
+pub use column_parsing::{Column, ColumnValue, Columns, RaggedRows};
 pub use dataset::{Dataset, ReadingOptions, Separator, TypedDataset};
 pub use default_typer::{ColumnType, DefaultTyper, Value};
 pub use defaults::read_file;
 pub use errors::Result;
-pub use record_parsing::RecordParsingOptions;
+pub use fixed_width::FixedWidths;
+pub use lexer::{
+    ByteOrderMark, RecordDiagnostic, RecordIssue, RecordPosition, RecordTerminator, TextEncoding,
+};
+pub use numeric_locale::NumericLocale;
+pub use record_parsing::{
+    MissingValues, QuoteEscape, RecordParsingOptions, Trim, Trimming, ValueSeparator,
+};
 pub use schema::{Schema, SchemaInferenceDepth};
 pub use typer::{DatasetValue, Typer};
-pub use value_parsing::{Parsed, RawValue};
+pub use value_parsing::{InvalidValue, ParseError, ParseErrorKind, Parsed, RawValue};
@@ -0,0 +1,250 @@
+use crate::dataset_file::DatasetFile;
+use crate::errors::Result;
+use crate::record_parsing::{RecordParser, RecordParsingOptions};
+use std::borrow::Cow;
+use std::path::Path;
+use tokio_stream::StreamExt;
+
+/// Number of records sampled while inferring the numeric locale
+const LOCALE_INFERENCE_SAMPLE_SIZE: usize = 1000;
+
+static COMMON_CURRENCY_SYMBOLS: [&str; 4] = ["$", "€", "£", "¥"];
+
+fn common_currency_symbols() -> Vec<String> {
+    COMMON_CURRENCY_SYMBOLS
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// How numbers are written
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NumericLocale {
+    /// Separates the integer part of a number from its fraction
+    pub decimal_mark: char,
+    /// Separates groups of three digits of the integer part, as in `1,234`
+    pub grouping_mark: Option<char>,
+    /// Symbols that can precede or follow a number, and are ignored
+    pub currency_symbols: Vec<String>,
+    /// Read numbers in parentheses, as in `(42.00)`, as negative
+    pub accounting_negatives: bool,
+    /// Read numbers followed by `%` as hundredths
+    pub percent: bool,
+}
+
+impl Default for NumericLocale {
+    fn default() -> Self {
+        NumericLocale {
+            decimal_mark: '.',
+            grouping_mark: None,
+            currency_symbols: vec![],
+            accounting_negatives: false,
+            percent: false,
+        }
+    }
+}
+
+/// Text of a number in the notation Rust parses
+pub(crate) struct PlainNumber<'a> {
+    pub(crate) text: Cow<'a, str>,
+    /// Whether the number is a percentage
+    pub(crate) percent: bool,
+}
+
+impl NumericLocale {
+    /// Numbers as in `$1,234.50`, `(42.00)` or `12.5%`
+    pub fn financial() -> NumericLocale {
+        NumericLocale {
+            decimal_mark: '.',
+            grouping_mark: Some(','),
+            currency_symbols: common_currency_symbols(),
+            accounting_negatives: true,
+            percent: true,
+        }
+    }
+
+    /// Numbers as in `1.234,56`, `12,50 €` or `12,5 %`
+    pub fn european() -> NumericLocale {
+        NumericLocale {
+            decimal_mark: ',',
+            grouping_mark: Some('.'),
+            currency_symbols: common_currency_symbols(),
+            accounting_negatives: true,
+            percent: true,
+        }
+    }
+
+    /// Infer the locale that reads the most values of a sample of the records as numbers,
+    /// preferring the default locale and then [[NumericLocale::financial]] on ties.
+    pub async fn infer(
+        file_path: impl AsRef<Path>,
+        parsing_options: &RecordParsingOptions,
+    ) -> Result<NumericLocale> {
+        let candidates = [
+            NumericLocale::default(),
+            NumericLocale::financial(),
+            NumericLocale::european(),
+        ];
+        let mut number_counts = [0; 3];
+        let mut records = DatasetFile::new(file_path, parsing_options)
+            .read_records()
+            .await?
+            .take(LOCALE_INFERENCE_SAMPLE_SIZE);
+        while let Some(record_res) = records.next().await {
+            let record = record_res?;
+            for value in RecordParser::new(&record, parsing_options).filter_map(|v| v.get()) {
+                for (ix, candidate) in candidates.iter().enumerate() {
+                    if value.parse_f64_with(candidate).is_some() {
+                        number_counts[ix] += 1;
+                    }
+                }
+            }
+        }
+
+        let best_ix = (0..candidates.len())
+            .rev()
+            .max_by_key(|ix| number_counts[*ix])
+            .unwrap_or_default();
+        Ok(candidates[best_ix].clone())
+    }
+
+    /// The number `text` is in this locale, written the way Rust parses it
+    pub(crate) fn plain_number<'a>(&self, text: &'a str) -> Option<PlainNumber<'a>> {
+        let mut number = text;
+        let mut negative = false;
+        if self.accounting_negatives {
+            if let Some(inner) = number.strip_prefix('(').and_then(|n| n.strip_suffix(')')) {
+                negative = true;
+                number = inner.trim();
+            }
+        }
+        let mut percent = false;
+        if self.percent {
+            if let Some(n) = number.strip_suffix('%') {
+                percent = true;
+                number = n.trim_end();
+            }
+        }
+        // A sign can precede the currency symbol, as in `-$5`
+        let (sign, unsigned) = match number.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", number),
+        };
+        if let Some(amount) = self.strip_currency(unsigned) {
+            negative ^= !sign.is_empty();
+            number = amount;
+        }
+
+        let (integer, fraction) = match number.split_once(self.decimal_mark) {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (number, None),
+        };
+        let grouping_mark = self.grouping_mark.filter(|mark| integer.contains(*mark));
+        if let Some(mark) = grouping_mark {
+            if !is_grouped(integer, mark) || fraction.is_some_and(|f| f.contains(mark)) {
+                return None;
+            }
+        }
+        if !negative && grouping_mark.is_none() && self.decimal_mark == '.' {
+            let text = Cow::Borrowed(number);
+            return Some(PlainNumber { text, percent });
+        }
+
+        let mut plain = String::with_capacity(number.len() + 1);
+        if negative {
+            plain.push('-');
+        }
+        for c in integer.chars().filter(|c| Some(*c) != grouping_mark) {
+            plain.push(c);
+        }
+        if let Some(fraction) = fraction {
+            plain.push('.');
+            plain.push_str(fraction);
+        }
+        // With another decimal mark, a point can't belong to the number
+        if self.decimal_mark != '.' && plain.matches('.').count() > fraction.iter().count() {
+            return None;
+        }
+        let text = Cow::Owned(plain);
+        Some(PlainNumber { text, percent })
+    }
+
+    fn strip_currency<'a>(&self, text: &'a str) -> Option<&'a str> {
+        self.currency_symbols.iter().find_map(|symbol| {
+            text.strip_prefix(symbol.as_str())
+                .map(str::trim_start)
+                .or_else(|| text.strip_suffix(symbol.as_str()).map(str::trim_end))
+        })
+    }
+}
+
+/// Whether `integer` is made of digits in groups of three, split by `mark`, after a first group of up to three
+fn is_grouped(integer: &str, mark: char) -> bool {
+    let integer = integer.strip_prefix(['-', '+']).unwrap_or(integer);
+    integer.split(mark).enumerate().all(|(ix, group)| {
+        let valid_len = if ix == 0 {
+            (1..=3).contains(&group.len())
+        } else {
+            group.len() == 3
+        };
+        valid_len && group.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value_parsing::{Parsed, RawValue};
+
+    #[test]
+    fn test_parses_numbers_in_locales() {
+        let financial = NumericLocale::financial();
+        let european = NumericLocale::european();
+        let values = vec![
+            ("1,234.50", &financial, Parsed::Some(1234.5)),
+            ("$1,234.50", &financial, Parsed::Some(1234.5)),
+            ("-$5", &financial, Parsed::Some(-5.0)),
+            ("(42.00)", &financial, Parsed::Some(-42.0)),
+            ("12.5%", &financial, Parsed::Some(0.125)),
+            ("1,23", &financial, Parsed::Invalid(None)),
+            ("1.234,56", &european, Parsed::Some(1234.56)),
+            ("12,50 €", &european, Parsed::Some(12.5)),
+            ("12,5 %", &european, Parsed::Some(0.125)),
+            ("1.5", &european, Parsed::Invalid(None)),
+        ];
+        for (raw, locale, expected) in values {
+            assert_eq!(
+                RawValue::from(raw).parse_f64_with(locale),
+                expected,
+                "{} failed the test",
+                raw
+            );
+        }
+        assert_eq!(
+            RawValue::from("1.234").parse_i64_with(&european),
+            Parsed::Some(1234)
+        );
+        assert_eq!(
+            RawValue::from("12%").parse_i64_with(&financial),
+            Parsed::Invalid(None)
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_numeric_locale_inference() -> Result<()> {
+        let options = RecordParsingOptions {
+            separator: ";".into(),
+            ..RecordParsingOptions::default()
+        };
+        assert_eq!(
+            NumericLocale::infer("datasets/prices-european.csv", &options).await?,
+            NumericLocale::european()
+        );
+        assert_eq!(
+            NumericLocale::infer("datasets/sales-10.csv", &RecordParsingOptions::default()).await?,
+            NumericLocale::default()
+        );
+
+        Ok(())
+    }
+}
@@ -1,152 +1,619 @@
-use crate::{lexer::Record, value_parsing::RawValue};
-use derive_more::From;
+use crate::aligned_table::aligned_cells;
+use crate::fixed_width::FixedWidths;
+use crate::lexer::{Record, RecordPosition, RecordTerminator, TextEncoding};
+use crate::value_parsing::{Parsed, RawValue};
+use regex::Regex;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// How a text quote is escaped inside a quoted value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum QuoteEscape {
+    /// By doubling the quote, as in RFC 4180
+    Doubled,
+    /// By a preceding backslash, which also escapes itself
+    #[default]
+    Backslash,
+    /// Quotes can't appear inside quoted values
+    None,
+}
+
+impl QuoteEscape {
+    /// Escape sequences recognized inside a value quoted with `text_quote`, each with the text it stands for
+    pub(crate) fn sequences(self, text_quote: &str) -> Vec<(String, String)> {
+        match self {
+            _ if text_quote.is_empty() => vec![],
+            QuoteEscape::Doubled => vec![(text_quote.repeat(2), text_quote.to_string())],
+            QuoteEscape::Backslash => vec![
+                (format!("\\{}", text_quote), text_quote.to_string()),
+                ("\\\\".to_string(), "\\".to_string()),
+            ],
+            QuoteEscape::None => vec![],
+        }
+    }
+}
+
+/// Whitespace trimmed from around a value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Trim {
+    #[default]
+    None,
+    Leading,
+    Trailing,
+    Both,
+    /// Both ends of unquoted values, keeping any whitespace inside quotes
+    Unquoted,
+}
+
+impl Trim {
+    pub(crate) fn apply<'a>(self, value: RawValue<'a>, quoted: bool) -> RawValue<'a> {
+        let trim: fn(&str) -> &str = match self {
+            Trim::None => return value,
+            Trim::Unquoted if quoted => return value,
+            Trim::Leading => str::trim_start,
+            Trim::Trailing => str::trim_end,
+            Trim::Both | Trim::Unquoted => str::trim,
+        };
+        match value.0 {
+            Cow::Borrowed(text) => trim(text).into(),
+            Cow::Owned(text) if trim(&text).len() == text.len() => text.into(),
+            Cow::Owned(text) => trim(&text).to_string().into(),
+        }
+    }
+}
+
+/// How the values of each column, header names included, are trimmed
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct Trimming {
+    /// Trimming of the columns without one of their own
+    pub default: Trim,
+    /// Trimming of specific columns, by index
+    pub columns: HashMap<usize, Trim>,
+}
+
+impl Trimming {
+    /// The same trimming for every column
+    pub fn all(trim: Trim) -> Trimming {
+        Trimming {
+            default: trim,
+            columns: HashMap::new(),
+        }
+    }
+
+    pub fn column(&self, ix: usize) -> Trim {
+        self.columns.get(&ix).copied().unwrap_or(self.default)
+    }
+}
+
+/// Tokens standing for missing values in each column, compared to unquoted values once trimmed
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct MissingValues {
+    /// Tokens of the columns without any of their own
+    pub default: Vec<String>,
+    /// Tokens of specific columns, by index
+    pub columns: HashMap<usize, Vec<String>>,
+}
+
+impl MissingValues {
+    /// The same tokens for every column
+    pub fn all(tokens: Vec<String>) -> MissingValues {
+        MissingValues {
+            default: tokens,
+            columns: HashMap::new(),
+        }
+    }
+
+    /// Tokens commonly used for missing values, such as `NA` or `null`, in every column
+    pub fn common() -> MissingValues {
+        let tokens = ["NA", "N/A", "null", "NULL", "-", "?", "#N/A"];
+        MissingValues::all(tokens.iter().map(|token| token.to_string()).collect())
+    }
+
+    pub fn column(&self, ix: usize) -> &[String] {
+        self.columns.get(&ix).unwrap_or(&self.default)
+    }
+}
+
+/// What separates the values of a record
+#[derive(Clone, Debug)]
+pub enum ValueSeparator {
+    Text(String),
+    /// Any of several texts, the longest winning where more than one match
+    AnyOf(Vec<String>),
+    /// Non-empty matches of a regular expression
+    Regex(Regex),
+    /// One or more whitespace characters, ignoring any around the record
+    Whitespace,
+    /// Whitespace between the columns of a table whose headers span the given character ranges
+    Aligned(Vec<Range<usize>>),
+}
+
+impl ValueSeparator {
+    /// Index and length of the first separator in `text`
+    fn find(&self, text: &str) -> Option<(usize, usize)> {
+        match self {
+            ValueSeparator::Text(separator) => text
+                .find(separator.as_str())
+                .map(|ix| (ix, separator.len())),
+            ValueSeparator::AnyOf(separators) => separators
+                .iter()
+                .filter(|separator| !separator.is_empty())
+                .filter_map(|separator| {
+                    text.find(separator.as_str())
+                        .map(|ix| (ix, separator.len()))
+                })
+                .min_by_key(|(ix, len)| (*ix, Reverse(*len))),
+            ValueSeparator::Regex(regex) => regex
+                .find_iter(text)
+                .find(|m| !m.is_empty())
+                .map(|m| (m.start(), m.len())),
+            ValueSeparator::Whitespace | ValueSeparator::Aligned(_) => {
+                let ix = text.find(char::is_whitespace)?;
+                Some((ix, whitespace_len(&text[ix..])))
+            }
+        }
+    }
+
+    /// Length of the separator that follows a value, given the text after it
+    fn len_after_value(&self, text: &str) -> usize {
+        match self {
+            ValueSeparator::Text(separator) => separator.len(),
+            ValueSeparator::AnyOf(separators) => separators
+                .iter()
+                .filter(|separator| text.starts_with(separator.as_str()))
+                .map(String::len)
+                .max()
+                .unwrap_or_default(),
+            ValueSeparator::Regex(regex) => regex
+                .find(text)
+                .filter(|m| m.start() == 0)
+                .map_or(0, |m| m.len()),
+            ValueSeparator::Whitespace | ValueSeparator::Aligned(_) => whitespace_len(text),
+        }
+    }
+}
+
+impl PartialEq for ValueSeparator {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ValueSeparator::Text(lhs), ValueSeparator::Text(rhs)) => lhs == rhs,
+            (ValueSeparator::AnyOf(lhs), ValueSeparator::AnyOf(rhs)) => lhs == rhs,
+            (ValueSeparator::Regex(lhs), ValueSeparator::Regex(rhs)) => {
+                lhs.as_str() == rhs.as_str()
+            }
+            (ValueSeparator::Whitespace, ValueSeparator::Whitespace) => true,
+            (ValueSeparator::Aligned(lhs), ValueSeparator::Aligned(rhs)) => lhs == rhs,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ValueSeparator {}
+
+impl From<&str> for ValueSeparator {
+    fn from(separator: &str) -> Self {
+        ValueSeparator::Text(separator.to_string())
+    }
+}
+
+impl From<String> for ValueSeparator {
+    fn from(separator: String) -> Self {
+        ValueSeparator::Text(separator)
+    }
+}
+
+fn whitespace_len(text: &str) -> usize {
+    text.len() - text.trim_start().len()
+}
 
 #[derive(Clone)]
 pub struct RecordParsingOptions {
-    pub separator: String,
+    pub separator: ValueSeparator,
     pub text_quote: String,
-    pub text_quote_escape: String,
+    pub quote_escape: QuoteEscape,
+    pub text_encoding: TextEncoding,
+    pub record_terminator: RecordTerminator,
+    /// Prefix of lines that are skipped as comments
+    pub comment_prefix: Option<String>,
+    pub skip_blank_lines: bool,
+    /// Number of leading lines to skip, regardless of their content
+    pub skip_lines: usize,
+    /// Recover from malformed records instead of failing
+    pub lenient: bool,
+    /// Longest record allowed, in bytes of UTF-8 text
+    pub max_record_len: Option<usize>,
+    /// Largest number of fields allowed in a record
+    pub max_fields: Option<usize>,
+    /// Decode backslash escapes such as `\t` in unquoted values, reading `\N` as a missing value,
+    /// as in the PostgreSQL text format
+    pub backslash_escapes: bool,
+    /// Columns of fixed-width text, read in place of values split at the separator
+    pub fixed_widths: Option<FixedWidths>,
+    pub trimming: Trimming,
+    pub missing_values: MissingValues,
 }
 
 impl Default for RecordParsingOptions {
     fn default() -> Self {
         RecordParsingOptions {
-            separator: ",".to_string(),
+            separator: ",".into(),
             text_quote: "\"".to_string(),
-            text_quote_escape: "\\".to_string(),
+            quote_escape: QuoteEscape::default(),
+            text_encoding: TextEncoding::default(),
+            record_terminator: RecordTerminator::default(),
+            comment_prefix: None,
+            skip_blank_lines: false,
+            skip_lines: 0,
+            lenient: false,
+            max_record_len: None,
+            max_fields: None,
+            backslash_escapes: false,
+            fixed_widths: None,
+            trimming: Trimming::default(),
+            missing_values: MissingValues::default(),
         }
     }
 }
 
-/// An iterator over a record from a CSV file that yields [[RawValue]] instances.
+/// An iterator over a record from a CSV file that yields [[RawValue]] instances, or missing values.
+///
+/// Values borrow from the record and are only copied when they have to be unescaped.
 pub struct RecordParser<'a> {
-    record: Record,
+    record: &'a Record,
     options: &'a RecordParsingOptions,
+    escape_sequences: Vec<(String, String)>,
     next_start: usize,
+    /// Index of the column of the next value
+    next_column: usize,
+    /// Byte ranges of the cells of an aligned table record, once split
+    aligned_cells: Option<Vec<Option<Range<usize>>>>,
+    /// Index of the end of the last yielded value
+    parsed_len: usize,
+    /// Whether the last yielded value was quoted
+    quoted: bool,
 }
 
 impl<'a> RecordParser<'a> {
-    pub fn new(record: Record, options: &'a RecordParsingOptions) -> RecordParser<'a> {
+    pub fn new(record: &'a Record, options: &'a RecordParsingOptions) -> RecordParser<'a> {
         RecordParser {
             record,
             options,
+            escape_sequences: options.quote_escape.sequences(&options.text_quote),
             next_start: 0,
+            next_column: 0,
+            aligned_cells: None,
+            parsed_len: 0,
+            quoted: false,
         }
     }
 }
 
 impl<'a> RecordParser<'a> {
-    fn remaining(&self) -> &str {
+    /// Location of the parsed record in the source file
+    pub fn position(&self) -> RecordPosition {
+        self.record.position()
+    }
+
+    /// Length of the text of the values yielded so far, separators between them included
+    pub(crate) fn parsed_len(&self) -> usize {
+        self.parsed_len
+    }
+
+    fn text(&self) -> &'a str {
+        self.record.as_ref()
+    }
+
+    fn remaining(&self) -> &'a str {
         self.start_from(self.next_start)
     }
 
-    fn start_from(&self, ix: usize) -> &str {
-        &self.record.as_ref()[ix..]
+    fn start_from(&self, ix: usize) -> &'a str {
+        &self.text()[ix..]
     }
 
     fn next_separator_ix(&self) -> Option<usize> {
-        self.remaining()
-            .find(&self.options.separator)
-            .map(|ix| ix + self.next_start)
+        let mut from = self.next_start;
+        loop {
+            let rest = self.start_from(from);
+            let separator_ix = self.options.separator.find(rest).map(|(ix, _)| ix);
+            match rest.find('\\') {
+                // A separator preceded by a backslash belongs to the value
+                Some(ix)
+                    if self.options.backslash_escapes && separator_ix.is_none_or(|s| ix < s) =>
+                {
+                    let escaped = &rest[ix + 1..];
+                    from += ix + 1 + escaped.chars().next().map_or(0, char::len_utf8);
+                }
+                _ => return separator_ix.map(|ix| ix + from),
+            }
+        }
     }
 
     fn next_quote_ix(&self) -> Option<usize> {
+        if self.options.text_quote.is_empty() {
+            return None;
+        }
         self.remaining()
             .find(&self.options.text_quote)
             .map(|ix| ix + self.next_start)
     }
 
-    fn subsequent_qoute_ix(&self, first_quote_ix: usize) -> Option<usize> {
-        self.start_from(first_quote_ix + self.options.text_quote.len())
-            .find(&self.options.text_quote)
-            .map(|ix| ix + first_quote_ix + self.options.text_quote.len())
+    /// Index of the quote that closes the value opened by the quote at `quote_l`, skipping escaped quotes
+    fn closing_quote_ix(&self, quote_l: usize) -> Option<usize> {
+        let quote = &self.options.text_quote;
+        let mut ix = quote_l + quote.len();
+        while let Some(rest) = self.text().get(ix..).filter(|r| !r.is_empty()) {
+            match escape_sequence_at(&self.escape_sequences, rest) {
+                Some((sequence, _)) => ix += sequence.len(),
+                None if rest.starts_with(quote.as_str()) => return Some(ix),
+                None => ix += rest.chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        None
     }
 
-    fn parse_unquoted(&self) -> (UnquotedRawValue, usize) {
+    fn parse_unquoted(&self) -> (UnquotedRawValue<'a>, usize) {
         let end = self
             .next_separator_ix()
-            .unwrap_or_else(|| self.record.as_ref().len());
-        let (raw, n) = self.parse_to(end);
-        (raw.into(), n)
+            .unwrap_or_else(|| self.text().len());
+        let unquoted = UnquotedRawValue {
+            raw: self.parse_to(end),
+            backslash_escapes: self.options.backslash_escapes,
+        };
+        (unquoted, end)
     }
 
-    fn parse_quoted(&self) -> Result<(QuotedRawValue, usize), ()> {
+    fn parse_quoted(&self) -> Result<(QuotedRawValue<'a, '_>, usize), ()> {
         let quote_l = self.next_quote_ix().ok_or(())?;
-        let mut quote_r = self.subsequent_qoute_ix(quote_l).ok_or(())?;
+        let quote_r = self.closing_quote_ix(quote_l).ok_or(())?;
+
+        let end = quote_r + self.options.text_quote.len();
+        let raw = self.parse_to(end);
+        let quoted = QuotedRawValue::new(raw, self.options, &self.escape_sequences);
+        Ok((quoted, end))
+    }
+
+    /// Yields the next fixed-width column, stripped of the spaces padding it
+    fn next_fixed_width(&mut self, fixed_widths: &FixedWidths) -> Option<Parsed<RawValue<'a>>> {
+        let column = fixed_widths.columns().get(self.next_column)?;
+        let text = self.text();
+        let is_ascii = text.is_ascii();
+        let byte_ix = |char_ix: usize| {
+            if is_ascii {
+                char_ix.min(text.len())
+            } else {
+                text.char_indices()
+                    .nth(char_ix)
+                    .map_or(text.len(), |(ix, _)| ix)
+            }
+        };
+        let (start, end) = (byte_ix(column.start), byte_ix(column.end));
+        let value = text[start..end.max(start)].trim_matches(' ').into();
+        self.next_column += 1;
+        self.next_start = end;
+        self.parsed_len = end;
+        Some(Parsed::Some(value))
+    }
 
-        while self.record.as_ref()[quote_r - self.options.text_quote_escape.len()..quote_r]
-            == self.options.text_quote_escape
-        {
-            quote_r = self.subsequent_qoute_ix(quote_r).ok_or(())?;
+    /// Yields the next cell of an aligned table, splitting the record into cells first
+    fn next_aligned(&mut self, headers: &[Range<usize>]) -> Option<Parsed<RawValue<'a>>> {
+        if self.aligned_cells.is_none() {
+            self.aligned_cells = Some(aligned_cells(self.text(), headers));
         }
+        let cell = self.aligned_cells.as_ref()?.get(self.next_column)?.clone();
+        let value = match cell {
+            Some(cell) => {
+                self.parsed_len = cell.end;
+                self.text()[cell].into()
+            }
+            None => "".into(),
+        };
+        self.next_column += 1;
+        Some(Parsed::Some(value))
+    }
 
-        let end = quote_r + self.options.text_quote.len();
-        let (raw, n) = self.parse_to(end);
-        let quoted = QuotedRawValue::new(raw, &self.options);
-        Ok((quoted, n))
+    /// End of any whitespace between the closing quote at `end` and the separator or the end of the record
+    fn padding_end(&self, end: usize) -> usize {
+        let rest = self.start_from(end);
+        let padding = whitespace_len(rest);
+        let padded = padding > 0
+            && (padding == rest.len()
+                || matches!(self.options.separator.find(&rest[padding..]), Some((0, _))));
+        if padded {
+            end + padding
+        } else {
+            end
+        }
+    }
+
+    fn parse_to(&self, end: usize) -> &'a str {
+        &self.text()[self.next_start..end]
     }
 
-    fn parse_to(&self, end: usize) -> (&str, usize) {
-        let value = &self.record.as_ref()[self.next_start..end];
-        (value, end + self.options.separator.len())
+    /// Index of the start of the value after the one ending at `end`, past the end of the record if there is none
+    fn next_value_start(&self, end: usize) -> usize {
+        let text = self.text();
+        match self.options.separator.len_after_value(&text[end..]) {
+            0 if end == text.len() => end + 1,
+            separator_len => end + separator_len,
+        }
     }
 }
 
 impl<'a> Iterator for RecordParser<'a> {
-    type Item = RawValue; // New Strings have to be allocated because escape patterns need to be dropped
+    type Item = Parsed<RawValue<'a>>;
     fn next(&mut self) -> Option<Self::Item> {
+        let column = self.next_column;
+        self.quoted = false;
+        let value = self.next_value()?;
+        // Quoted values are always text, even when they read as a token
+        let missing_values = match self.quoted {
+            true => &[],
+            false => self.options.missing_values.column(column),
+        };
+        match value {
+            Parsed::Some(value) if missing_values.iter().any(|token| value.0.trim() == token) => {
+                Some(Parsed::Missing)
+            }
+            value => Some(value),
+        }
+    }
+}
+
+impl<'a> RecordParser<'a> {
+    fn next_value(&mut self) -> Option<Parsed<RawValue<'a>>> {
         if self.next_start > self.record.len() || self.record.is_empty() {
             return None;
         }
-        let (value, next_start) = if self.next_separator_ix() < self.next_quote_ix() {
+        if let Some(fixed_widths) = &self.options.fixed_widths {
+            return self.next_fixed_width(fixed_widths);
+        }
+        match &self.options.separator {
+            ValueSeparator::Aligned(headers) => return self.next_aligned(headers),
+            ValueSeparator::Whitespace => {
+                self.next_start += whitespace_len(self.remaining());
+                if self.remaining().is_empty() {
+                    return None;
+                }
+            }
+            _ => {}
+        }
+        let separator_first = matches!(
+            (self.next_separator_ix(), self.next_quote_ix()),
+            (Some(separator_ix), Some(quote_ix)) if separator_ix < quote_ix
+        );
+        let (value, end, quoted) = if separator_first {
             let (raw, n) = self.parse_unquoted();
-            (raw.into(), n)
+            (raw.into(), n, false)
         } else {
             self.parse_quoted()
-                .map(|(raw, n)| (raw.into(), n))
+                .map(|(raw, n)| (Parsed::Some(raw.into()), self.padding_end(n), true))
                 .unwrap_or_else(|_| {
                     let (raw, n) = self.parse_unquoted();
-                    (raw.into(), n)
+                    (raw.into(), n, false)
                 })
         };
+        let trim = self.options.trimming.column(self.next_column);
+        let value = value.map(|value| trim.apply(value, quoted));
+        self.quoted = quoted;
 
-        self.next_start = next_start;
+        self.next_column += 1;
+        self.parsed_len = end;
+        self.next_start = self.next_value_start(end);
         Some(value)
     }
 }
 
-#[derive(From)]
-struct UnquotedRawValue<'a>(&'a str);
+struct UnquotedRawValue<'a> {
+    raw: &'a str,
+    backslash_escapes: bool,
+}
 
-impl<'a> From<UnquotedRawValue<'a>> for RawValue {
+impl<'a> From<UnquotedRawValue<'a>> for Parsed<RawValue<'a>> {
     fn from(v: UnquotedRawValue<'a>) -> Self {
-        RawValue(v.0.to_string())
+        match v.raw {
+            "\\N" if v.backslash_escapes => Parsed::Missing,
+            raw if v.backslash_escapes => Parsed::Some(RawValue(unescape_backslashes(raw))),
+            raw => Parsed::Some(raw.into()),
+        }
     }
 }
 
-struct QuotedRawValue<'a> {
+struct QuotedRawValue<'a, 's> {
     raw: &'a str,
     options: &'a RecordParsingOptions,
+    escape_sequences: &'s [(String, String)],
 }
 
-impl<'a> From<QuotedRawValue<'a>> for RawValue {
-    fn from(v: QuotedRawValue<'a>) -> RawValue {
+impl<'a, 's> From<QuotedRawValue<'a, 's>> for RawValue<'a> {
+    fn from(v: QuotedRawValue<'a, 's>) -> RawValue<'a> {
         let quote_l = v.raw.find(&v.options.text_quote);
         let quote_r = v.raw.rfind(&v.options.text_quote);
         match (quote_l, quote_r) {
-            (Some(ix_l), Some(ix_r)) if ix_l < ix_r => v.raw
-                [ix_l + v.options.text_quote.len()..ix_r]
-                .replace(&v.options.text_quote_escape, "")
-                .into(),
-            _ => v.raw.to_string().into(),
+            (Some(ix_l), Some(ix_r)) if ix_l < ix_r => RawValue(unescape(
+                &v.raw[ix_l + v.options.text_quote.len()..ix_r],
+                v.escape_sequences,
+            )),
+            _ => v.raw.into(),
         }
     }
 }
 
-impl<'a> QuotedRawValue<'a> {
-    fn new(raw: &'a str, options: &'a RecordParsingOptions) -> QuotedRawValue<'a> {
-        QuotedRawValue { raw, options }
+impl<'a, 's> QuotedRawValue<'a, 's> {
+    fn new(
+        raw: &'a str,
+        options: &'a RecordParsingOptions,
+        escape_sequences: &'s [(String, String)],
+    ) -> QuotedRawValue<'a, 's> {
+        QuotedRawValue {
+            raw,
+            options,
+            escape_sequences,
+        }
+    }
+}
+
+/// Decodes the backslash escapes of the PostgreSQL text format. Any other escaped character stands for itself.
+fn unescape_backslashes(text: &str) -> Cow<'_, str> {
+    if !text.contains('\\') {
+        return Cow::Borrowed(text);
+    }
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('b') => '\u{8}',
+                Some('f') => '\u{c}',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('v') => '\u{b}',
+                Some(escaped) => escaped,
+                None => '\\',
+            },
+            c => c,
+        };
+        unescaped.push(c);
+    }
+    Cow::Owned(unescaped)
+}
+
+/// The escape sequence `text` starts with, if any
+fn escape_sequence_at<'s>(
+    escape_sequences: &'s [(String, String)],
+    text: &str,
+) -> Option<&'s (String, String)> {
+    escape_sequences
+        .iter()
+        .find(|(sequence, _)| text.starts_with(sequence.as_str()))
+}
+
+/// Replaces the escape sequences in `text` with the text they stand for, leaving everything else alone
+fn unescape<'t>(text: &'t str, escape_sequences: &[(String, String)]) -> Cow<'t, str> {
+    if !escape_sequences
+        .iter()
+        .any(|(sequence, _)| text.contains(sequence.as_str()))
+    {
+        return Cow::Borrowed(text);
     }
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        match escape_sequence_at(escape_sequences, rest) {
+            Some((sequence, replacement)) => {
+                unescaped.push_str(replacement);
+                rest = &rest[sequence.len()..];
+            }
+            None => {
+                unescaped.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    Cow::Owned(unescaped)
 }
 
 #[cfg(test)]
@@ -158,11 +625,12 @@ mod tests {
     fn test_record_values_1() {
         let record = "first, second,,three,4,,,".into();
         let parsing_options = RecordParsingOptions {
-            separator: ",".to_string(),
+            separator: ",".into(),
             text_quote: "\"".to_string(),
-            text_quote_escape: "\\".to_string(),
+            ..RecordParsingOptions::default()
         };
-        let values: Vec<String> = RecordParser::new(record, &&parsing_options)
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
             .map_into()
             .collect();
 
@@ -176,11 +644,12 @@ mod tests {
     fn test_record_values_2() {
         let record = "first, second,,three,4,,,five".into();
         let parsing_options = RecordParsingOptions {
-            separator: ",".to_string(),
+            separator: ",".into(),
             text_quote: "\"".to_string(),
-            text_quote_escape: "\\".to_string(),
+            ..RecordParsingOptions::default()
         };
-        let values: Vec<String> = RecordParser::new(record, &&parsing_options)
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
             .map_into()
             .collect();
 
@@ -194,11 +663,12 @@ mod tests {
     fn test_record_values_3() {
         let record = "first,, second,,,,three,,4,,,,,,".into();
         let parsing_options = RecordParsingOptions {
-            separator: ",,".to_string(),
+            separator: ",,".into(),
             text_quote: "\"".to_string(),
-            text_quote_escape: "\\".to_string(),
+            ..RecordParsingOptions::default()
         };
-        let values: Vec<String> = RecordParser::new(record, &&parsing_options)
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
             .map_into()
             .collect();
 
@@ -212,11 +682,12 @@ mod tests {
     fn test_record_values_4() {
         let record = "first, second,,three,4,\"\",,five".into();
         let parsing_options = RecordParsingOptions {
-            separator: ",".to_string(),
+            separator: ",".into(),
             text_quote: "\"".to_string(),
-            text_quote_escape: "\\".to_string(),
+            ..RecordParsingOptions::default()
         };
-        let values: Vec<String> = RecordParser::new(record, &&parsing_options)
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
             .map_into()
             .collect();
 
@@ -230,11 +701,12 @@ mod tests {
     fn test_record_values_5() {
         let record = "first, \"second point five\",,three,4,\"\",,five".into();
         let parsing_options = RecordParsingOptions {
-            separator: ",".to_string(),
+            separator: ",".into(),
             text_quote: "\"".to_string(),
-            text_quote_escape: "\\".to_string(),
+            ..RecordParsingOptions::default()
         };
-        let values: Vec<String> = RecordParser::new(record, &parsing_options)
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
             .map_into()
             .collect();
 
@@ -257,11 +729,12 @@ mod tests {
     fn test_record_values_6() {
         let record = "first, \"second \\\" point five\",,three,4,\"\",,five".into();
         let parsing_options = RecordParsingOptions {
-            separator: ",".to_string(),
+            separator: ",".into(),
             text_quote: "\"".to_string(),
-            text_quote_escape: "\\".to_string(),
+            ..RecordParsingOptions::default()
         };
-        let values: Vec<String> = RecordParser::new(record, &parsing_options)
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
             .map_into()
             .collect();
 
@@ -284,11 +757,12 @@ mod tests {
     fn test_record_values_7() {
         let record = "first, \"second \\\" \\\" point five\",,three,4,\"\",,five".into();
         let parsing_options = RecordParsingOptions {
-            separator: ",".to_string(),
+            separator: ",".into(),
             text_quote: "\"".to_string(),
-            text_quote_escape: "\\".to_string(),
+            ..RecordParsingOptions::default()
         };
-        let values: Vec<String> = RecordParser::new(record, &parsing_options)
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
             .map_into()
             .collect();
 
@@ -306,4 +780,203 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_record_values_with_doubled_quotes() {
+        let record = "\"say \"\"hi\"\"\",\"\",\"C:\\dir\\\",\"\"\"\"".into();
+        let parsing_options = RecordParsingOptions {
+            quote_escape: QuoteEscape::Doubled,
+            ..RecordParsingOptions::default()
+        };
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
+            .map_into()
+            .collect();
+
+        assert_eq!(values, vec!["say \"hi\"", "", "C:\\dir\\", "\""])
+    }
+
+    #[test]
+    fn test_record_values_with_backslash_escapes() {
+        let record = "\"C:\\dir\\\\\",\"a \\\" b\",\"\\n\"".into();
+        let parsing_options = RecordParsingOptions {
+            quote_escape: QuoteEscape::Backslash,
+            ..RecordParsingOptions::default()
+        };
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
+            .map_into()
+            .collect();
+
+        assert_eq!(values, vec!["C:\\dir\\", "a \" b", "\\n"])
+    }
+
+    #[test]
+    fn test_record_values_without_quote_escapes() {
+        let record = "\"a \\\",b".into();
+        let parsing_options = RecordParsingOptions {
+            quote_escape: QuoteEscape::None,
+            ..RecordParsingOptions::default()
+        };
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
+            .map_into()
+            .collect();
+
+        assert_eq!(values, vec!["a \\", "b"])
+    }
+
+    #[test]
+    fn test_record_values_with_backslash_escapes_in_unquoted_values() {
+        let record = "a\\tb\t\\N\tc\\\\d\\\te\t\\x".into();
+        let parsing_options = RecordParsingOptions {
+            separator: "\t".into(),
+            text_quote: "".to_string(),
+            backslash_escapes: true,
+            ..RecordParsingOptions::default()
+        };
+        let values: Vec<Parsed<String>> = RecordParser::new(&record, &parsing_options)
+            .map(|value| value.map(String::from))
+            .collect();
+
+        assert_eq!(
+            values,
+            vec![
+                Parsed::Some("a\tb".to_string()),
+                Parsed::Missing,
+                Parsed::Some("c\\d\te".to_string()),
+                Parsed::Some("x".to_string()),
+            ]
+        )
+    }
+
+    #[test]
+    fn test_record_values_separated_by_whitespace() {
+        let record = "  first \t\"second point five\"   three 4  ".into();
+        let parsing_options = RecordParsingOptions {
+            separator: ValueSeparator::Whitespace,
+            ..RecordParsingOptions::default()
+        };
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
+            .map_into()
+            .collect();
+
+        assert_eq!(values, vec!["first", "second point five", "three", "4"])
+    }
+
+    #[test]
+    fn test_record_values_separated_by_any_of() {
+        let record = "first;\"second; or, third\",,three;;4".into();
+        let parsing_options = RecordParsingOptions {
+            separator: ValueSeparator::AnyOf(vec![
+                ",".to_string(),
+                ";".to_string(),
+                ";;".to_string(),
+            ]),
+            ..RecordParsingOptions::default()
+        };
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
+            .map_into()
+            .collect();
+
+        assert_eq!(values, vec!["first", "second; or, third", "", "three", "4"])
+    }
+
+    #[test]
+    fn test_record_values_separated_by_regex() {
+        let record = "first | \"second | third\"  |three|4 |".into();
+        let parsing_options = RecordParsingOptions {
+            separator: ValueSeparator::Regex(Regex::new(r"\s*\|\s*").unwrap()),
+            ..RecordParsingOptions::default()
+        };
+        let values: Vec<String> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
+            .map_into()
+            .collect();
+
+        assert_eq!(values, vec!["first", "second | third", "three", "4", ""])
+    }
+
+    #[test]
+    fn test_record_values_borrow_unless_unescaped() {
+        let record = "1,\"two\",\"th\"\"ree\"".into();
+        let parsing_options = RecordParsingOptions {
+            quote_escape: QuoteEscape::Doubled,
+            ..RecordParsingOptions::default()
+        };
+        let borrowed: Vec<bool> = RecordParser::new(&record, &parsing_options)
+            .filter_map(Parsed::get)
+            .map(|value| matches!(value.0, Cow::Borrowed(_)))
+            .collect();
+
+        assert_eq!(borrowed, vec![true, true, false])
+    }
+
+    #[test]
+    fn test_record_values_trimmed() {
+        let record = " first , \" second \" ,three ".into();
+        let values = |trimming: Trimming| -> Vec<String> {
+            let parsing_options = RecordParsingOptions {
+                trimming,
+                ..RecordParsingOptions::default()
+            };
+            RecordParser::new(&record, &parsing_options)
+                .filter_map(Parsed::get)
+                .map_into()
+                .collect()
+        };
+
+        assert_eq!(
+            values(Trimming::all(Trim::None)),
+            vec![" first ", " second ", "three "]
+        );
+        assert_eq!(
+            values(Trimming::all(Trim::Leading)),
+            vec!["first ", "second ", "three "]
+        );
+        assert_eq!(
+            values(Trimming::all(Trim::Trailing)),
+            vec![" first", " second", "three"]
+        );
+        assert_eq!(
+            values(Trimming::all(Trim::Both)),
+            vec!["first", "second", "three"]
+        );
+        assert_eq!(
+            values(Trimming::all(Trim::Unquoted)),
+            vec!["first", " second ", "three"]
+        );
+        assert_eq!(
+            values(Trimming {
+                default: Trim::Both,
+                columns: HashMap::from([(2, Trim::None)]),
+            }),
+            vec!["first", "second", "three "]
+        );
+    }
+
+    #[test]
+    fn test_record_values_missing_tokens() {
+        let record = "NA, null ,\"?\",n/a,\"NA\"".into();
+        let parsing_options = RecordParsingOptions {
+            missing_values: MissingValues::common(),
+            ..RecordParsingOptions::default()
+        };
+        let values: Vec<Parsed<String>> = RecordParser::new(&record, &parsing_options)
+            .map(|value| value.map(String::from))
+            .collect();
+
+        assert_eq!(
+            values,
+            vec![
+                Parsed::Missing,
+                Parsed::Missing,
+                Parsed::Some("?".to_string()),
+                Parsed::Some("n/a".to_string()),
+                Parsed::Some("NA".to_string())
+            ]
+        )
+    }
 }
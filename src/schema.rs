@@ -1,17 +1,15 @@
 use crate::errors::Result;
 use crate::record_parsing::{RecordParser, RecordParsingOptions};
-use crate::typer::{DatasetValue, Typer};
+use crate::typer::Typer;
 use crate::value_parsing::Parsed;
 use crate::{
     dataset_file::{DatasetFile, RecordsToRead},
     lexer::Record,
 };
 use futures_core::TryStream;
-use itertools::Itertools;
-use maplit::hashmap;
 use rayon::current_num_threads;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use tokio::task;
 use tokio_stream::StreamExt;
@@ -25,6 +23,7 @@ impl<T: Typer + Send + Sync> Schema<T> {
     pub async fn infer(
         file_path: impl AsRef<Path>,
         skip_header: bool,
+        column_names: &[String],
         inference_depth: &SchemaInferenceDepth,
         parsing_options: &RecordParsingOptions,
         typer: &T,
@@ -37,8 +36,8 @@ impl<T: Typer + Send + Sync> Schema<T> {
         let own_file_path = file_path.as_ref().to_owned();
         let own_typer = typer.clone();
         let own_parsing_options = parsing_options.clone();
-        let column_type_counts = task::spawn_blocking(move || {
-            count_file_column_types_blocking(
+        let column_samples = task::spawn_blocking(move || {
+            sample_file_columns_blocking(
                 own_file_path,
                 skip_header,
                 records_to_read,
@@ -49,16 +48,20 @@ impl<T: Typer + Send + Sync> Schema<T> {
         .await
         .expect("Failed to join on a blocking task")?;
 
-        let column_types = column_type_counts
+        let column_types = column_samples
             .0
-            .into_iter()
-            .map(|types| {
-                types
-                    .into_iter()
-                    .sorted_by_key(|(_, count)| *count)
-                    .last()
-                    .map(|(column_type, _)| column_type)
-                    .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(ix, sample)| {
+                let name = column_names.get(ix).map(String::as_str);
+                let column_type = typer.column_type(&sample.type_counts, sample.value_count, name);
+                sample
+                    .categories
+                    .as_ref()
+                    .and_then(|categories| {
+                        typer.categorical_type(column_type, categories.len(), sample.value_count)
+                    })
+                    .unwrap_or(column_type)
             })
             .collect();
 
@@ -66,73 +69,126 @@ impl<T: Typer + Send + Sync> Schema<T> {
     }
 }
 
-fn count_file_column_types_blocking<T: Typer + Send + Sync>(
-    file_path: impl AsRef<Path> + Clone + Sized,
+fn sample_file_columns_blocking<T: Typer + Send + Sync>(
+    file_path: impl AsRef<Path> + Clone,
     skip_header: bool,
     records_to_read: RecordsToRead,
     parsing_options: &RecordParsingOptions,
     typer: &T,
-) -> Result<ColumnTypeCounts<T>> {
+) -> Result<ColumnSamples<T>> {
     let batch_count = current_num_threads();
-    let dataset_file = DatasetFile::new(file_path);
+    let dataset_file = DatasetFile::new(file_path, parsing_options);
     let record_batches =
         dataset_file.batches_blocking(skip_header, records_to_read, batch_count)?;
 
-    let batch_column_type_counts: Vec<Result<ColumnTypeCounts<T>>> = record_batches
+    let batch_column_samples: Vec<Result<ColumnSamples<T>>> = record_batches
         .into_par_iter()
         .map(|batch| match batch.read_records_blocking() {
-            Ok(records) => count_column_types(records, typer, parsing_options),
+            Ok(records) => sample_columns(records, typer, parsing_options),
             Err(err) => Err(err),
         })
         .collect();
 
-    let mut output = ColumnTypeCounts::default();
-    for column_type_counts in batch_column_type_counts.into_iter() {
-        output.update_with(column_type_counts?)
+    let mut output = ColumnSamples::default();
+    for column_samples in batch_column_samples.into_iter() {
+        output.update_with(column_samples?, typer.max_categories())
     }
 
     Ok(output)
 }
 
 #[tokio::main(flavor = "current_thread")]
-async fn count_column_types<T: Typer>(
+async fn sample_columns<T: Typer>(
     mut records: impl TryStream<Item = Result<Record>> + Unpin,
     typer: &T,
     parsing_options: &RecordParsingOptions,
-) -> Result<ColumnTypeCounts<T>> {
-    let mut column_type_counts: Vec<HashMap<T::ColumnType, usize>> = Vec::new();
+) -> Result<ColumnSamples<T>> {
+    let max_categories = typer.max_categories();
+    let mut column_samples: Vec<ColumnSample<T>> = Vec::new();
     while let Some(record_res) = records.next().await {
-        let record_values = RecordParser::new(record_res?, &parsing_options);
+        let record = record_res?;
+        let record_values = RecordParser::new(&record, parsing_options);
         for (ix, val) in record_values.enumerate() {
-            if let Parsed::Some(parsed) = typer.parse(&val) {
-                let column_type = parsed.get_column_type();
-                match column_type_counts.get_mut(ix) {
-                    Some(counts) => *counts.entry(parsed.get_column_type()).or_default() += 1,
-                    None => column_type_counts.push(hashmap! { column_type => 1 }),
+            if column_samples.len() <= ix {
+                column_samples.resize_with(ix + 1, || ColumnSample::new(max_categories));
+            }
+            // Blank values are missing values and are not sampled
+            if let Parsed::Some(val) = val {
+                if val.0.trim().is_empty() {
+                    continue;
                 }
+                let sample = &mut column_samples[ix];
+                sample.value_count += 1;
+                let type_counts = &mut sample.type_counts;
+                typer.count_types(&val, &mut |column_type| {
+                    *type_counts.entry(column_type).or_default() += 1
+                });
+                sample.add_category(&val.0, max_categories);
+            }
+        }
+    }
+    Ok(ColumnSamples(column_samples))
+}
+
+/// How prevalent each parsable column type is in a column, how many of its values were sampled,
+/// and its distinct values while there are few enough to be categories
+#[derive(Debug)]
+struct ColumnSample<T: Typer> {
+    type_counts: HashMap<T::ColumnType, usize>,
+    value_count: usize,
+    categories: Option<HashSet<String>>,
+}
+
+impl<T: Typer> ColumnSample<T> {
+    fn new(max_categories: Option<usize>) -> Self {
+        ColumnSample {
+            type_counts: HashMap::new(),
+            value_count: 0,
+            categories: max_categories.map(|_| HashSet::new()),
+        }
+    }
+
+    /// Keeps track of a distinct value, until there are more than `max_categories`
+    fn add_category(&mut self, value: &str, max_categories: Option<usize>) {
+        if let Some(categories) = &mut self.categories {
+            if !categories.contains(value) {
+                categories.insert(value.to_string());
             }
         }
+        self.drop_extra_categories(max_categories);
+    }
+
+    fn drop_extra_categories(&mut self, max_categories: Option<usize>) {
+        let too_many = |categories: &HashSet<String>| Some(categories.len()) > max_categories;
+        if self.categories.as_ref().is_some_and(too_many) {
+            self.categories = None;
+        }
     }
-    Ok(ColumnTypeCounts(column_type_counts))
 }
 
-/// A mapping of each parsable column types to how prevalent it is in each column, ordered by column order.
+/// Samples of each column, ordered by column order.
 #[derive(Default, Debug)]
-struct ColumnTypeCounts<T: Typer>(Vec<HashMap<T::ColumnType, usize>>);
+struct ColumnSamples<T: Typer>(Vec<ColumnSample<T>>);
 
-impl<T: Typer> ColumnTypeCounts<T> {
-    fn update_with(&mut self, other: Self) {
+impl<T: Typer> ColumnSamples<T> {
+    fn update_with(&mut self, other: Self, max_categories: Option<usize>) {
         for (col_ix, rhs_col) in other.0.into_iter().enumerate() {
-            for (t, t_counts) in rhs_col.into_iter() {
-                let type_counts = match self.0.get_mut(col_ix) {
-                    Some(counts) => counts,
-                    None => {
-                        self.0.push(HashMap::new());
-                        self.0.get_mut(col_ix).unwrap()
-                    }
-                };
-                *type_counts.entry(t).or_default() += t_counts;
+            if self.0.len() <= col_ix {
+                self.0.push(ColumnSample::new(max_categories));
             }
+            let sample = &mut self.0[col_ix];
+            for (t, t_counts) in rhs_col.type_counts.into_iter() {
+                *sample.type_counts.entry(t).or_default() += t_counts;
+            }
+            sample.value_count += rhs_col.value_count;
+            sample.categories = match (sample.categories.take(), rhs_col.categories) {
+                (Some(mut categories), Some(rhs_categories)) => {
+                    categories.extend(rhs_categories);
+                    Some(categories)
+                }
+                _ => None,
+            };
+            sample.drop_extra_categories(max_categories);
         }
     }
 }
@@ -166,6 +222,7 @@ mod test {
         let schema = Schema::infer(
             "datasets/sales-100.csv",
             skip_first_record,
+            &[],
             &schema_inference_depth,
             &parsing_options,
             &typer,
@@ -179,9 +236,9 @@ mod test {
                 ColumnType::Text,
                 ColumnType::Text,
                 ColumnType::Text,
-                ColumnType::Text,
+                ColumnType::Date("%m/%d/%Y"),
                 ColumnType::Int,
-                ColumnType::Text,
+                ColumnType::Date("%m/%d/%Y"),
                 ColumnType::Int,
                 ColumnType::Float,
                 ColumnType::Float,
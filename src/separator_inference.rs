@@ -1,41 +1,195 @@
 use crate::dataset_file::DatasetFile;
 use crate::errors::Result;
+use crate::record_parsing::{RecordParsingOptions, ValueSeparator};
 use itertools::Itertools;
-use std::collections::HashMap;
+use regex::Regex;
+use std::ops::Range;
 use std::path::Path;
 use tokio_stream::StreamExt;
 
-static COMMON_SEPARATORS: [&str; 3] = [",", "\t", "|"];
+static COMMON_SEPARATORS: [&str; 5] = [",", "\t", "|", ";", "\u{1f}"];
 
-/// Infer the separator as the most commonly used separator in the file
-pub async fn infer_separator(path: impl AsRef<Path>) -> Result<String> {
-    let mut counts: HashMap<&str, usize> = HashMap::default();
-    let mut records = DatasetFile::new(path).read_records().await?;
+/// Infer the separator as the most commonly used separator in the file.
+///
+/// Two separators that are used interchangeably, so that together they occur as often in every
+/// record, are proposed as alternatives. A separator that is always padded with spaces is proposed
+/// together with its padding.
+///
+/// Records are framed using the quoting rules of the given options, and separators inside quoted
+/// values aren't counted; their separator is ignored.
+pub async fn infer_separator(
+    path: impl AsRef<Path>,
+    options: &RecordParsingOptions,
+) -> Result<ValueSeparator> {
+    // Occurrences of each common separator in each non-empty record
+    let mut counts: Vec<[usize; COMMON_SEPARATORS.len()]> = vec![];
+    let mut always_padded = [true; COMMON_SEPARATORS.len()];
+    let mut records = DatasetFile::new(path, options).read_records().await?;
     while let Some(record_res) = records.next().await {
         let record = record_res?;
-        for sep in COMMON_SEPARATORS.iter() {
-            *counts.entry(sep).or_default() += record.as_ref().matches(sep).count();
+        let text = record.as_ref();
+        if text.is_empty() {
+            continue;
         }
+        let quoted = quoted_spans(text, options);
+        let unquoted = |start: &usize| !quoted.iter().any(|span| span.contains(start));
+        let mut record_counts = [0; COMMON_SEPARATORS.len()];
+        for (ix, sep) in COMMON_SEPARATORS.iter().enumerate() {
+            for start in text
+                .match_indices(sep)
+                .map(|(start, _)| start)
+                .filter(unquoted)
+            {
+                record_counts[ix] += 1;
+                let end = start + sep.len();
+                always_padded[ix] &= text[..start].ends_with(' ') && text[end..].starts_with(' ');
+            }
+        }
+        counts.push(record_counts);
     }
-    let sep = counts
-        .into_iter()
-        .sorted_by_key(|(_, v)| *v)
+
+    let total = |ix: usize| counts.iter().map(|c| c[ix]).sum::<usize>();
+    let sep_ix = match (0..COMMON_SEPARATORS.len())
+        .filter(|ix| total(*ix) > 0)
+        .sorted_by_key(|ix| total(*ix))
         .last()
-        .map(|(k, _)| k)
-        .unwrap_or(",");
-    Ok(sep.to_string())
+    {
+        Some(sep_ix) => sep_ix,
+        None => return Ok(",".into()),
+    };
+    let sep = COMMON_SEPARATORS[sep_ix];
+
+    let is_consistent = |ixs: &[usize]| {
+        counts
+            .iter()
+            .map(|c| ixs.iter().map(|ix| c[*ix]).sum::<usize>())
+            .all_equal()
+    };
+    let alternative = (0..COMMON_SEPARATORS.len()).find(|ix| {
+        *ix != sep_ix
+            && total(*ix) > 0
+            && !is_consistent(&[sep_ix])
+            && is_consistent(&[sep_ix, *ix])
+    });
+
+    let separator = match alternative {
+        Some(ix) => ValueSeparator::AnyOf(vec![sep.to_string(), COMMON_SEPARATORS[ix].to_string()]),
+        None if always_padded[sep_ix] => {
+            let padded = format!(" *{} *", regex::escape(sep));
+            ValueSeparator::Regex(
+                Regex::new(&padded).expect("Escaped separators make valid patterns"),
+            )
+        }
+        None => sep.into(),
+    };
+    Ok(separator)
+}
+
+/// Byte ranges of the values of `text` quoted with the text quote of `options`, quotes included.
+///
+/// A quote only opens a value at the start of the record or after a common separator, spaces aside.
+fn quoted_spans(text: &str, options: &RecordParsingOptions) -> Vec<Range<usize>> {
+    let quote = options.text_quote.as_str();
+    if quote.is_empty() {
+        return vec![];
+    }
+    let escapes = options.quote_escape.sequences(quote);
+    let mut spans = vec![];
+    let mut ix = 0;
+    while let Some(offset) = text[ix..].find(quote) {
+        let start = ix + offset;
+        ix = start + quote.len();
+        let before = text[..start].trim_end_matches(' ');
+        let opens = before.is_empty() || COMMON_SEPARATORS.iter().any(|sep| before.ends_with(sep));
+        if !opens {
+            continue;
+        }
+        // The value runs to the next quote that isn't part of an escape sequence, or to the end
+        let mut end = text.len();
+        while ix < text.len() {
+            let rest = &text[ix..];
+            if let Some((escape, _)) = escapes.iter().find(|(e, _)| rest.starts_with(e.as_str())) {
+                ix += escape.len();
+            } else if rest.starts_with(quote) {
+                ix += quote.len();
+                end = ix;
+                break;
+            } else {
+                ix += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        spans.push(start..end);
+    }
+    spans
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::lexer::RecordTerminator;
+    use crate::record_parsing::QuoteEscape;
 
     #[tokio::test]
     pub async fn test_separator_inference() -> Result<()> {
-        assert_eq!(infer_separator("datasets/sales-100.tsv").await?, "\t");
+        let options = RecordParsingOptions::default();
+        assert_eq!(
+            infer_separator("datasets/sales-100.tsv", &options).await?,
+            "\t".into()
+        );
+        assert_eq!(
+            infer_separator("datasets/sales-100.csv", &options)
+                .await
+                .unwrap(),
+            ",".into()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_separator_inference_with_record_terminators() -> Result<()> {
+        let options = RecordParsingOptions::default();
+        assert_eq!(
+            infer_separator("datasets/sales-10-cr.csv", &options).await?,
+            ",".into()
+        );
+
+        let options = RecordParsingOptions {
+            record_terminator: RecordTerminator::Custom("\u{1e}".to_string()),
+            ..RecordParsingOptions::default()
+        };
+        assert_eq!(
+            infer_separator("datasets/sales-10-rs-us.dat", &options).await?,
+            "\u{1f}".into()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_separator_inference_of_alternatives_and_padding() -> Result<()> {
+        let options = RecordParsingOptions::default();
+        assert_eq!(
+            infer_separator("datasets/mixed-separators.csv", &options).await?,
+            ValueSeparator::AnyOf(vec![";".to_string(), ",".to_string()])
+        );
+        assert_eq!(
+            infer_separator("datasets/padded-separators.txt", &options).await?,
+            ValueSeparator::Regex(Regex::new(r" *\| *").unwrap())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_separator_inference_ignores_quoted_values() -> Result<()> {
+        let options = RecordParsingOptions {
+            quote_escape: QuoteEscape::Doubled,
+            ..RecordParsingOptions::default()
+        };
         assert_eq!(
-            infer_separator("datasets/sales-100.csv").await.unwrap(),
-            ","
+            infer_separator("datasets/quoted-separators.csv", &options).await?,
+            ",".into()
         );
 
         Ok(())
@@ -1,4 +1,6 @@
-use crate::value_parsing::{Parsed, RawValue};
+use crate::numeric_locale::NumericLocale;
+use crate::value_parsing::{ParseErrorKind, Parsed, RawValue};
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
 
@@ -8,17 +10,82 @@ pub trait Typer: Default + Clone + Debug + Send + Sync + 'static {
 
     const COLUMN_TYPES: &'static [Self::ColumnType];
 
-    fn parse_as(&self, value: &RawValue, tag: Self::ColumnType) -> Parsed<Self::DatasetValue>;
+    fn parse_as(&self, value: &RawValue<'_>, tag: Self::ColumnType) -> Parsed<Self::DatasetValue>;
 
-    fn parse(&self, value: &RawValue) -> Parsed<Self::DatasetValue> {
+    /// Typer that reads numbers written as in `locale`, which by default is this typer as is
+    fn localize(&self, _locale: &NumericLocale) -> Self {
+        self.clone()
+    }
+
+    fn parse(&self, value: &RawValue<'_>) -> Parsed<Self::DatasetValue> {
         Self::COLUMN_TYPES
             .iter()
             .map(|tag| self.parse_as(value, *tag))
             .find(|v| v.is_some())
-            .unwrap_or(Parsed::Invalid)
+            .unwrap_or(Parsed::Invalid(None))
+    }
+
+    /// Why `value` is invalid as `tag`, which by default is that it is malformed
+    fn parse_error_kind(&self, _value: &RawValue<'_>, _tag: Self::ColumnType) -> ParseErrorKind {
+        ParseErrorKind::Malformed
+    }
+
+    /// Calls `count` with each type `value` counts toward while inferring the type of its column,
+    /// which by default is the type it parses as
+    fn count_types(&self, value: &RawValue<'_>, count: &mut dyn FnMut(Self::ColumnType)) {
+        if let Parsed::Some(parsed) = self.parse(value) {
+            count(parsed.get_column_type());
+        }
+    }
+
+    /// Type of a column from how many of its `value_count` values were parsed as each type, given
+    /// the name of the column when the dataset has a header
+    fn column_type(
+        &self,
+        type_counts: &HashMap<Self::ColumnType, usize>,
+        _value_count: usize,
+        _name: Option<&str>,
+    ) -> Self::ColumnType {
+        type_counts
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(column_type, _)| *column_type)
+            .unwrap_or_default()
+    }
+
+    /// Most distinct values a column can have to be inferred as categorical, or `None` when the
+    /// typer has no categorical types
+    fn max_categories(&self) -> Option<usize> {
+        None
+    }
+
+    /// Categorical type of a column inferred as `column_type`, whose `value_count` sampled values
+    /// take `category_count` distinct ones, if it should be one
+    fn categorical_type(
+        &self,
+        _column_type: Self::ColumnType,
+        _category_count: usize,
+        _value_count: usize,
+    ) -> Option<Self::ColumnType> {
+        None
+    }
+
+    /// Value of the category of `code` in a column of type `tag`, for the types of columns whose
+    /// values are stored as codes into [[crate::Column::categories]]
+    fn category_value(&self, _tag: Self::ColumnType, _code: u32) -> Option<Self::DatasetValue> {
+        None
     }
 }
 
 pub trait DatasetValue<C>: Debug + Clone + PartialEq + Send + Sync {
     fn get_column_type(&self) -> C;
+
+    /// Code of the value into the categories of its column, if it is stored as one
+    fn category_code(&self) -> Option<u32> {
+        None
+    }
+
+    fn category_code_mut(&mut self) -> Option<&mut u32> {
+        None
+    }
 }
@@ -1,70 +1,220 @@
+use crate::boolean_vocabulary::BooleanVocabulary;
+use crate::numeric_locale::NumericLocale;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
 use derive_more::{Display, From, Into};
+use rust_decimal::Decimal;
+use std::borrow::Cow;
+use std::str::FromStr;
 
-/// A CSV value
+/// A CSV value, borrowed from the text of its record unless it had to be unescaped
 #[derive(Debug, Clone, Hash, PartialEq, Eq, From, Into, Display)]
-pub struct RawValue(pub String);
+pub struct RawValue<'a>(pub Cow<'a, str>);
 
-impl RawValue {
-    pub fn parse_bool(&self) -> Parsed<bool> {
-        match self.0.trim().to_lowercase().as_ref() {
+impl<'a> RawValue<'a> {
+    /// Parses the trimmed value, which is missing when empty
+    fn parse_trimmed<T, E>(&self, parse: impl FnOnce(&str) -> Result<T, E>) -> Parsed<T> {
+        match self.0.trim() {
             "" => Parsed::Missing,
-            "1" | "t" => Parsed::Some(true),
-            "0" | "f" => Parsed::Some(false),
-            otherwise => otherwise
-                .parse()
+            otherwise => parse(otherwise)
                 .map(Parsed::Some)
-                .unwrap_or(Parsed::Invalid),
+                .unwrap_or(Parsed::Invalid(None)),
         }
     }
 
-    pub fn parse_i64(&self) -> Parsed<i64> {
+    /// Parses an exact number written as in `locale`, reading percentages as hundredths
+    pub fn parse_decimal_with(&self, locale: &NumericLocale) -> Parsed<Decimal> {
+        self.parse_trimmed(|text| {
+            let number = locale.plain_number(text).ok_or(())?;
+            let decimal: Decimal = number.text.parse().map_err(|_| ())?;
+            match number.percent {
+                true => decimal.checked_div(Decimal::ONE_HUNDRED).ok_or(()),
+                false => Ok(decimal),
+            }
+        })
+    }
+
+    pub fn parse_date(&self, format: &str) -> Parsed<NaiveDate> {
+        self.parse_trimmed(|text| NaiveDate::parse_from_str(text, format))
+    }
+
+    pub fn parse_time(&self, format: &str) -> Parsed<NaiveTime> {
+        self.parse_trimmed(|text| NaiveTime::parse_from_str(text, format))
+    }
+
+    pub fn parse_datetime(&self, format: &str) -> Parsed<NaiveDateTime> {
+        self.parse_trimmed(|text| NaiveDateTime::parse_from_str(text, format))
+    }
+
+    /// Parses a date and time with an offset, which is UTC when the format ends with a literal `Z`,
+    /// as in RFC 3339
+    pub fn parse_datetime_tz(&self, format: &str) -> Parsed<DateTime<FixedOffset>> {
+        if format.ends_with('Z') && !format.ends_with("%Z") {
+            return self.parse_trimmed(|text| {
+                NaiveDateTime::parse_from_str(text, format)
+                    .map(|datetime| datetime.and_utc().fixed_offset())
+            });
+        }
+        self.parse_trimmed(|text| DateTime::parse_from_str(text, format))
+    }
+
+    pub fn parse_bool(&self) -> Parsed<bool> {
+        self.parse_bool_with(&BooleanVocabulary::default())
+    }
+
+    pub fn parse_bool_with(&self, vocabulary: &BooleanVocabulary) -> Parsed<bool> {
         match self.0.trim() {
             "" => Parsed::Missing,
-            otherwise => otherwise
-                .parse()
+            otherwise => vocabulary
+                .parse(otherwise)
                 .map(Parsed::Some)
-                .unwrap_or(Parsed::Invalid),
+                .unwrap_or(Parsed::Invalid(None)),
         }
     }
 
+    pub fn parse_i64(&self) -> Parsed<i64> {
+        self.parse_i64_with(&NumericLocale::default())
+    }
+
+    /// Parses an integer written as in `locale`, where percentages are never integers
+    pub fn parse_i64_with(&self, locale: &NumericLocale) -> Parsed<i64> {
+        self.parse_integer_with(locale)
+    }
+
+    pub fn parse_u64_with(&self, locale: &NumericLocale) -> Parsed<u64> {
+        self.parse_integer_with(locale)
+    }
+
+    pub fn parse_i128_with(&self, locale: &NumericLocale) -> Parsed<i128> {
+        self.parse_integer_with(locale)
+    }
+
+    fn parse_integer_with<N: FromStr>(&self, locale: &NumericLocale) -> Parsed<N> {
+        self.parse_trimmed(|text| match locale.plain_number(text) {
+            Some(number) if !number.percent => number.text.parse().map_err(|_| ()),
+            _ => Err(()),
+        })
+    }
+
+    /// Parses an integer written in hexadecimal, octal or binary, as in `0x1F`, `0o17` or `0b101`
+    pub fn parse_radix_literal(&self) -> Parsed<i128> {
+        self.parse_trimmed(|text| {
+            let (negative, unsigned) = match text.strip_prefix('-') {
+                Some(unsigned) => (true, unsigned),
+                None => (false, text.strip_prefix('+').unwrap_or(text)),
+            };
+            let radix = match unsigned.get(..2).ok_or(())? {
+                "0x" | "0X" => 16,
+                "0o" | "0O" => 8,
+                "0b" | "0B" => 2,
+                _ => return Err(()),
+            };
+            let digits = &unsigned[2..];
+            if digits.starts_with(['-', '+']) {
+                return Err(());
+            }
+            let magnitude = i128::from_str_radix(digits, radix).map_err(|_| ())?;
+            match negative {
+                true => magnitude.checked_neg().ok_or(()),
+                false => Ok(magnitude),
+            }
+        })
+    }
+
+    /// The value as an owned string, only copying it if it is borrowed
+    pub fn into_owned(self) -> String {
+        self.0.into_owned()
+    }
+
     pub fn parse_f64(&self) -> Parsed<f64> {
-        match self.0.trim().to_lowercase().as_ref() {
+        self.parse_f64_with(&NumericLocale::default())
+    }
+
+    /// Parses a number written as in `locale`, reading percentages as hundredths
+    pub fn parse_f64_with(&self, locale: &NumericLocale) -> Parsed<f64> {
+        match self.0.trim() {
             "" => Parsed::Missing,
-            "nan" => Parsed::Some(f64::NAN),
-            otherwise => otherwise
-                .parse()
-                .map(Parsed::Some)
-                .unwrap_or(Parsed::Invalid),
+            nan if nan.eq_ignore_ascii_case("nan") => Parsed::Some(f64::NAN),
+            otherwise => match locale.plain_number(otherwise) {
+                Some(number) => number
+                    .text
+                    .parse::<f64>()
+                    .map(|n| Parsed::Some(if number.percent { n / 100.0 } else { n }))
+                    .unwrap_or(Parsed::Invalid(None)),
+                None => Parsed::Invalid(None),
+            },
         }
     }
 }
 
-impl From<&str> for RawValue {
-    fn from(s: &str) -> Self {
-        s.to_string().into()
+impl<'a> From<&'a str> for RawValue<'a> {
+    fn from(s: &'a str) -> Self {
+        RawValue(Cow::Borrowed(s))
     }
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
-pub enum Parsed<A> {
-    Invalid,
+impl From<String> for RawValue<'_> {
+    fn from(s: String) -> Self {
+        RawValue(Cow::Owned(s))
+    }
+}
+
+impl From<RawValue<'_>> for String {
+    fn from(v: RawValue<'_>) -> Self {
+        v.into_owned()
+    }
+}
+
+/// Why a value could not be parsed as the type of its column
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, Display)]
+pub enum ParseErrorKind {
+    /// The value isn't written the way values of the type are
+    #[display(fmt = "malformed")]
+    Malformed,
+    /// The value is of the right kind but too large or too precise for the type
+    #[display(fmt = "out of range")]
+    OutOfRange,
+}
+
+/// Why a value could not be parsed as `C`, the type of its column
+#[derive(Clone, Hash, PartialEq, Eq, Debug, Display)]
+#[display(fmt = "{} {}", kind, expected)]
+pub struct ParseError<C> {
+    /// Type the value was parsed as
+    pub expected: C,
+    pub kind: ParseErrorKind,
+}
+
+/// A value that could not be parsed as `C`, the type of its column, along with why
+#[derive(Clone, Hash, PartialEq, Eq, Debug, Display)]
+#[display(fmt = "{:?}: {}", "raw.0", error)]
+pub struct InvalidValue<C> {
+    pub raw: RawValue<'static>,
+    pub error: ParseError<C>,
+}
+
+/// A value parsed as `A`, which when it is invalid can say why it could not be parsed as `C`, the
+/// type of its column
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub enum Parsed<A, C = ()> {
+    /// A value that could not be parsed, with its text and the error when invalid values are kept
+    Invalid(Option<Box<InvalidValue<C>>>),
     Missing,
     Some(A),
 }
 
-impl<T> Parsed<T> {
+impl<T, C> Parsed<T, C> {
     pub fn get(self) -> Option<T> {
         match self {
             Parsed::Missing => None,
-            Parsed::Invalid => None,
+            Parsed::Invalid(_) => None,
             Parsed::Some(x) => Some(x),
         }
     }
 
-    pub fn or_else(self, op: impl FnOnce() -> Parsed<T>) -> Parsed<T> {
+    pub fn or_else(self, op: impl FnOnce() -> Parsed<T, C>) -> Parsed<T, C> {
         match self {
             Parsed::Missing => op(),
-            Parsed::Invalid => op(),
+            Parsed::Invalid(_) => op(),
             some => some,
         }
     }
@@ -72,19 +222,43 @@ impl<T> Parsed<T> {
     pub fn unwrap_or_else(self, op: impl FnOnce() -> T) -> T {
         match self {
             Parsed::Missing => op(),
-            Parsed::Invalid => op(),
+            Parsed::Invalid(_) => op(),
             Parsed::Some(t) => t,
         }
     }
 
-    pub fn map<U>(self, op: impl FnOnce(T) -> U) -> Parsed<U> {
+    pub fn map<U>(self, op: impl FnOnce(T) -> U) -> Parsed<U, C> {
         match self {
             Parsed::Missing => Parsed::Missing,
-            Parsed::Invalid => Parsed::Invalid,
+            Parsed::Invalid(invalid) => Parsed::Invalid(invalid),
             Parsed::Some(t) => Parsed::Some(op(t)),
         }
     }
 
+    /// Replaces the text and the error of an invalid value with those `op` makes of them
+    pub fn map_invalid<D>(
+        self,
+        op: impl FnOnce(Option<Box<InvalidValue<C>>>) -> Option<Box<InvalidValue<D>>>,
+    ) -> Parsed<T, D> {
+        match self {
+            Parsed::Missing => Parsed::Missing,
+            Parsed::Invalid(invalid) => Parsed::Invalid(op(invalid)),
+            Parsed::Some(t) => Parsed::Some(t),
+        }
+    }
+
+    /// The text of the value and why it is invalid, when it is and they were kept
+    pub fn invalid_value(&self) -> Option<&InvalidValue<C>> {
+        match self {
+            Parsed::Invalid(invalid) => invalid.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, Parsed::Invalid(_))
+    }
+
     pub fn is_some(&self) -> bool {
         matches!(self, Parsed::Some(_))
     }